@@ -1,19 +1,21 @@
 #import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_clip}
 
-const ATLAS_TEXTURES_PER_SIDE: f32 = 16.0;
-
 struct Vertex {
     @builtin(instance_index) instance_index: u32,
     @location(0) position: vec3<f32>,
     @location(1) normals: vec3<f32>,
     @location(2) tex_coords: vec2<f32>,
     @location(3) atlas_index: u32,
+    @location(4) ambient_occlusion: f32,
+    @location(5) light: vec4<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
-    @location(1) atlas_index: u32,
+    @location(1) @interpolate(flat) atlas_index: u32,
+    @location(2) ambient_occlusion: f32,
+    @location(3) light: vec4<f32>,
 };
 
 @vertex
@@ -25,37 +27,47 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     );
     out.tex_coords = vertex.tex_coords;
     out.atlas_index = vertex.atlas_index;
+    out.ambient_occlusion = vertex.ambient_occlusion;
+    out.light = vertex.light;
     return out;
 }
 
-struct CustomMaterial {
-    texture_size: vec2<f32>,
-};
-
-@group(2) @binding(100) var<uniform> material: CustomMaterial;
-@group(2) @binding(101) var atlas_texture: texture_2d<f32>;
+@group(2) @binding(101) var atlas_texture: texture_2d_array<f32>;
 @group(2) @binding(102) var atlas_texture_sampler: sampler;
 
 struct FragmentInput {
     @location(0) tex_coords: vec2<f32>,
-    @location(1) atlas_index: u32,
+    @location(1) @interpolate(flat) atlas_index: u32,
+    @location(2) ambient_occlusion: f32,
+    @location(3) light: vec4<f32>,
 };
 
+// Brightness of a vertex tucked into a corner, fully occluded by its three neighbours
+const MAX_OCCLUSION_BRIGHTNESS: f32 = 0.45;
+
+// Every light level below full brightness is this much darker than the one above it
+const LIGHT_LEVEL_FALLOFF: f32 = 0.8;
+
+// Light levels scaled to 0..1, sky light in x & red, green & blue block light in yzw.
+// Sky light is white, so tinted block light only shows where it outshines the sky.
+fn light_brightness(light: vec4<f32>) -> vec3<f32> {
+    let levels = max(vec3<f32>(light.x), light.yzw) * 15.0;
+    return pow(vec3<f32>(LIGHT_LEVEL_FALLOFF), vec3<f32>(15.0) - levels);
+}
+
 @fragment
 fn fragment(input: FragmentInput) -> @location(0) vec4<f32> {
-    let atlas_index: f32 = f32(input.atlas_index); // 3
-    let texture_size: vec2<f32> = material.texture_size; // 256, 256
-    let atlas_textures_per_side: vec2<f32> = vec2<f32>(ATLAS_TEXTURES_PER_SIDE, ATLAS_TEXTURES_PER_SIDE); // 8, 8
-    let section_size: vec2<f32> = texture_size / atlas_textures_per_side; // 16, 16
+    // Greedy quads have UVs spanning several blocks, repeat sampling tiles the texture across them
+    let color = textureSample(atlas_texture, atlas_texture_sampler, input.tex_coords, input.atlas_index);
 
-    let atlas_x: f32 = atlas_index % ATLAS_TEXTURES_PER_SIDE; // 1
-    let atlas_y: f32 = floor(atlas_index / ATLAS_TEXTURES_PER_SIDE); // 0
+#ifdef MAY_DISCARD
+    // Alpha-masked material: cut-out parts of non-cube models are left out
+    if color.a < 0.5 {
+        discard;
+    }
+#endif
 
-    let section_from: vec2<f32> = vec2<f32>(atlas_x, atlas_y) * section_size; // 16, 0
-    let section_uv_offset: vec2<f32> = (section_size * input.tex_coords % section_size); // 16, 0 * 0.75, 0.5 = 12, 0
+    let brightness = mix(1.0, MAX_OCCLUSION_BRIGHTNESS, input.ambient_occlusion / 3.0) * light_brightness(input.light);
 
-    let section_uv: vec2<f32> = (section_from + section_uv_offset); // 28, 0
-
-//    return textureSample(atlas_texture, atlas_texture_sampler, input.tex_coords);
-    return textureSample(atlas_texture, atlas_texture_sampler, section_uv / texture_size);
-}
\ No newline at end of file
+    return vec4<f32>(color.rgb * brightness, color.a);
+}
@@ -0,0 +1,49 @@
+//! Generates the terrain around spawn & reports how much memory the paletted block data of its chunks takes,
+//! compared to storing a block ID or a block info pointer per block & to the voxels meshing copies it into.
+//!
+//! Run with `cargo bench --bench chunk_memory`.
+
+use std::mem::size_of;
+use std::sync::Arc;
+use std::time::Instant;
+
+use bevy::prelude::*;
+use block_mesh::ndshape::ConstShape;
+
+use client::block_info::{BlockId, BlockInfo, BlockInfoRegistry};
+use client::world::chunk::{ChunkBlockData, ChunkBlockShape, ChunkPosition, ChunkVoxelData};
+use client::world::generator::{HeightmapWorldGenerator, WorldGenerator, DEFAULT_WORLD_SEED};
+
+const RADIUS: i32 = 8;
+
+fn main() {
+    let registry = BlockInfoRegistry::initialize().expect("initialize block info registry");
+    let generator = HeightmapWorldGenerator::default();
+
+    let positions = (-RADIUS..RADIUS)
+        .flat_map(|x| (-2..2).flat_map(move |y| (-RADIUS..RADIUS).map(move |z| IVec3::new(x, y, z))));
+
+    let start = Instant::now();
+    let chunks = positions
+        .map(|position| generator.generate_chunk(DEFAULT_WORLD_SEED, &ChunkPosition(position), &registry))
+        .collect::<Vec<ChunkBlockData>>();
+    let generation_time = start.elapsed();
+
+    let sizes = chunks.iter().map(ChunkBlockData::memory_usage).collect::<Vec<_>>();
+    let total_size = sizes.iter().sum::<usize>();
+    let smallest_size = sizes.iter().min().copied().unwrap_or_default();
+    let largest_size = sizes.iter().max().copied().unwrap_or_default();
+    let uniform_count = sizes.iter().filter(|&&size| size == size_of::<ChunkBlockData>()).count();
+
+    let id_size = ChunkBlockShape::USIZE * size_of::<BlockId>();
+    let pointer_size = ChunkBlockShape::USIZE * size_of::<Option<Arc<BlockInfo>>>();
+
+    println!("{} chunks generated in {generation_time:?}, {uniform_count} of them uniform", chunks.len());
+    println!(
+        "paletted block data: {} bytes on average, {smallest_size} to {largest_size} bytes, {total_size} bytes in total",
+        total_size / chunks.len().max(1),
+    );
+    println!("a block ID per block: {id_size} bytes per chunk, {} bytes in total", id_size * chunks.len());
+    println!("a block info pointer per block: {pointer_size} bytes per chunk, {} bytes in total", pointer_size * chunks.len());
+    println!("voxels of a chunk being meshed: {} bytes", size_of::<ChunkVoxelData>());
+}
@@ -0,0 +1,133 @@
+//! Detonates a 16 x 3 x 16 grid of TNT in a headless app running the game's own explosion, light & meshing
+//! systems, reporting how long the frames of the chain reaction take & how many chunks got re-meshed.
+//!
+//! Run with `cargo bench --bench tnt_grid`.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use client::block_info::BlockInfoRegistry;
+use client::material::GlobalBlockAtlasMaterial;
+use client::world::chunk::{split_block_position, Chunk, ChunkBlockData, ChunkMeshDirty, ChunkMeshTask, ChunkPosition};
+use client::world::chunk_map::ChunkMap;
+use client::world::explosion::{detonate_primed_explosives, PrimedExplosives};
+use client::world::generator::{HeightmapWorldGenerator, WorldGenerator, DEFAULT_WORLD_SEED};
+use client::world::lifecycle::ChunkLifecycles;
+use client::world::light::update_light;
+use client::world::systems::{finish_chunk_mesh_tasks, mark_changed_chunks_dirty, remesh_dirty_chunks, BlockUpdate, ChunkTaskBudget};
+
+const FRAME_TIME: Duration = Duration::from_millis(50);
+const MAX_FRAMES: usize = 10_000;
+
+/// Chunks `remesh_dirty_chunks` started re-meshing so far
+#[derive(Default, Resource)]
+struct Remeshes(usize);
+
+fn count_remeshes(dirty_chunks: Query<(), With<ChunkMeshDirty>>, mut remeshes: ResMut<Remeshes>) {
+    remeshes.0 += dirty_chunks.iter().count();
+}
+
+fn main() {
+    let registry = BlockInfoRegistry::initialize().expect("initialize block info registry");
+    let tnt = registry.get_block_info("potato_crust:tnt");
+    let explosive = tnt.explosive.expect("TNT is explosive");
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Mesh>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+        .insert_resource(registry.clone())
+        .insert_resource(GlobalBlockAtlasMaterial {
+            opaque: Handle::default(),
+            cutout: Handle::default(),
+            translucent: Handle::default(),
+        })
+        .init_resource::<ChunkMap>()
+        .init_resource::<ChunkLifecycles>()
+        .init_resource::<ChunkTaskBudget>()
+        .init_resource::<PrimedExplosives>()
+        .init_resource::<Remeshes>()
+        .add_event::<BlockUpdate>()
+        .add_systems(
+            Update,
+            (detonate_primed_explosives, mark_changed_chunks_dirty, update_light, count_remeshes, remesh_dirty_chunks, finish_chunk_mesh_tasks).chain(),
+        );
+
+    // Generated terrain around the grid, each TNT just within the blast radius of the next
+    let generator = HeightmapWorldGenerator::default();
+    let mut chunks = (-1..=4)
+        .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=4).map(move |z| IVec3::new(x, y, z))))
+        .map(|chunk_position| (chunk_position, generator.generate_chunk(DEFAULT_WORLD_SEED, &ChunkPosition(chunk_position), &registry)))
+        .collect::<bevy::utils::HashMap<_, _>>();
+
+    let grid_positions = (0..16).flat_map(|x| (0..3).flat_map(move |y| (0..16).map(move |z| IVec3::new(x, y, z) * 4 + IVec3::splat(2))));
+    for block_position in grid_positions {
+        let (chunk_position, local_position) = split_block_position(block_position);
+        chunks.get_mut(&chunk_position).expect("grid is within the generated chunks").set(local_position, tnt.id);
+    }
+
+    for (chunk_position, block_data) in chunks {
+        let entity = app.world_mut().spawn(Chunk::from_block_data(chunk_position, block_data)).id();
+        app.world_mut().resource_mut::<ChunkMap>().insert(chunk_position, entity);
+    }
+
+    // Lighting & meshing the freshly loaded chunks isn't part of the measurement
+    let start = Instant::now();
+    let warm_up_frames = run_until_settled(&mut app, |_, _| {});
+    println!("initial light & meshes: {warm_up_frames} frames, {:?}", start.elapsed());
+
+    app.world_mut().resource_mut::<PrimedExplosives>().ignite(IVec3::splat(2), explosive.fuse_seconds);
+
+    app.world_mut().resource_mut::<Remeshes>().0 = 0;
+    let (mut explosion_frames, mut removed_blocks) = (0, 0);
+    let (mut total_frame_time, mut slowest_frame_time) = (Duration::ZERO, Duration::ZERO);
+
+    let start = Instant::now();
+    let frames = run_until_settled(&mut app, |app, frame_time| {
+        let frame_removed_blocks = app.world().resource::<Events<BlockUpdate>>().iter_current_update_events().count();
+
+        explosion_frames += (frame_removed_blocks > 0) as usize;
+        removed_blocks += frame_removed_blocks;
+        total_frame_time += frame_time;
+        slowest_frame_time = slowest_frame_time.max(frame_time);
+    });
+    let total_time = start.elapsed();
+    let remeshes = app.world().resource::<Remeshes>().0;
+
+    println!(
+        "{frames} frames, {explosion_frames} with explosions: removed {removed_blocks} blocks with {remeshes} chunk re-meshes \
+         in {total_time:?}, frames took {:?} on average & {slowest_frame_time:?} at most",
+        total_frame_time / frames.max(1) as u32,
+    );
+
+    let mut block_data = app.world_mut().query::<&ChunkBlockData>();
+    let is_tnt_left = block_data.iter(app.world()).any(|block_data| block_data.iter().any(|block_id| block_id == tnt.id));
+    assert!(!is_tnt_left, "chain reaction didn't reach every TNT");
+}
+
+fn count<F: bevy::ecs::query::QueryFilter>(app: &mut App) -> usize {
+    app.world_mut().query_filtered::<(), F>().iter(app.world()).count()
+}
+
+/// Updates the app until no explosives are left burning & every chunk got re-meshed, calling `on_frame` with
+/// the time each frame took. Returns the number of frames it took.
+fn run_until_settled(app: &mut App, mut on_frame: impl FnMut(&mut App, Duration)) -> usize {
+    for frame in 1..=MAX_FRAMES {
+        let start = Instant::now();
+        app.update();
+        let frame_time = start.elapsed();
+
+        on_frame(app, frame_time);
+
+        let is_settled = app.world().resource::<PrimedExplosives>().is_empty()
+            && count::<Or<(With<ChunkMeshDirty>, With<ChunkMeshTask>)>>(app) == 0;
+
+        if is_settled {
+            return frame;
+        }
+    }
+
+    panic!("still not settled after {MAX_FRAMES} frames");
+}
@@ -1,12 +1,9 @@
-use bevy::asset::LoadState;
+use bevy::asset::{LoadState, LoadedFolder, RecursiveDependencyLoadState};
 use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use crate::atlas::BlockTextureAtlas;
 use crate::block_info::BlockInfoPlugin;
 
-pub const ATLAS_TEXTURE_DIRT: u32 = 0;
-pub const ATLAS_TEXTURE_GRASS_SIDE: u32 = 1;
-pub const ATLAS_TEXTURE_GRASS_TOP: u32 = 2;
-pub const ATLAS_TEXTURE_COBBLESTONE: u32 = 3;
-
 #[derive(Default, Clone, Copy, Debug, Eq, Hash, PartialEq, States)]
 pub enum AppState {
     #[default]
@@ -14,27 +11,63 @@ pub enum AppState {
     InGame,
 }
 
+/// Folder of block textures, stitched into the `BlockTextureAtlas` once loaded
+#[derive(Resource)]
+pub struct BlockTexturesFolder(pub Handle<LoadedFolder>);
+
+/// Folder of `*.blocks.ron` files the block info registry gets built from
 #[derive(Resource)]
-pub struct BlockTextureAtlasImage(pub Handle<Image>);
+pub struct BlockDefinitionsFolder(pub Handle<LoadedFolder>);
 
 pub fn load_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
     debug!("Loaded assets");
 
-    let handle = asset_server.load("textures/experiment-block-atlas.png");
-    // let handle = asset_server.load("textures/grass-side.png");
-    commands.insert_resource(BlockTextureAtlasImage(handle));
+    commands.insert_resource(BlockTexturesFolder(asset_server.load_folder("textures/blocks")));
+    commands.insert_resource(BlockDefinitionsFolder(asset_server.load_folder("blocks")));
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn check_loaded_assets(
+    mut commands: Commands,
     mut next_state: ResMut<NextState<AppState>>,
-    atlas_image_handle: Res<BlockTextureAtlasImage>,
+    mut images: ResMut<Assets<Image>>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    block_textures_folder: Res<BlockTexturesFolder>,
+    block_definitions_folder: Res<BlockDefinitionsFolder>,
     asset_server: Res<AssetServer>,
+    render_device: Res<RenderDevice>,
 ) {
     debug!("Checking if all assets are loaded");
-    if let Some(LoadState::Loaded) = asset_server.get_load_state(&atlas_image_handle.0) {
-        debug!("All assets are loaded!");
-        next_state.set(AppState::InGame);
+
+    let are_block_textures_loaded = match asset_server.get_recursive_dependency_load_state(&block_textures_folder.0) {
+        Some(RecursiveDependencyLoadState::Loaded) => true,
+        Some(RecursiveDependencyLoadState::Failed) => panic!("Failed to load block textures, see the asset errors above"),
+        _ => false,
+    };
+
+    // Definition files that failed to parse were already reported by the asset server, the rest still get registered
+    let are_block_definitions_loaded = loaded_folders.get(&block_definitions_folder.0).is_some_and(|folder| {
+        folder
+            .handles
+            .iter()
+            .all(|handle| matches!(asset_server.get_load_state(handle), Some(LoadState::Loaded | LoadState::Failed(_))))
+    });
+
+    if !are_block_textures_loaded || !are_block_definitions_loaded {
+        return;
     }
+
+    debug!("All assets are loaded!");
+
+    let block_textures = loaded_folders
+        .get(&block_textures_folder.0)
+        .expect("block textures folder not loaded");
+    let max_layers = render_device.limits().max_texture_array_layers;
+    let block_texture_atlas = BlockTextureAtlas::from_folder(block_textures, &mut images, max_layers)
+        .unwrap_or_else(|error| panic!("Failed to build block texture atlas: {}", error));
+
+    commands.insert_resource(block_texture_atlas);
+    next_state.set(AppState::InGame);
 }
 
 #[derive(Default)]
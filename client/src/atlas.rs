@@ -0,0 +1,344 @@
+use bevy::asset::LoadedFolder;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureViewDescriptor, TextureViewDimension};
+use bevy::utils::HashMap;
+use thiserror::Error;
+
+/// Checkerboard texture every atlas gets, for the missing block placeholder
+pub const MISSING_TEXTURE_NAME: &str = "missing";
+
+#[derive(Debug, Error)]
+pub enum BlockTextureAtlasError {
+    #[error("no block textures to build the atlas from")]
+    NoTextures,
+    #[error("{0} block textures don't fit into the atlas, the GPU supports at most {1} texture array layers")]
+    TooManyTextures(usize, u32),
+    #[error("block texture `{0}` is defined more than once")]
+    DuplicateTexture(String),
+    #[error("block texture `{name}` is {width}x{height}, expected {expected}x{expected} like the other textures")]
+    SizeMismatch {
+        name: String,
+        width: u32,
+        height: u32,
+        expected: u32,
+    },
+    #[error("block texture `{0}` has format {1:?}, only {:?} is supported", TextureFormat::Rgba8UnormSrgb)]
+    UnsupportedFormat(String, TextureFormat),
+}
+
+/// Block textures stacked into the layers of a single texture array, with each texture's layer index
+#[derive(Clone, Debug, Resource)]
+pub struct BlockTextureAtlas {
+    pub image: Handle<Image>,
+    texture_indices: HashMap<String, u32>,
+}
+
+impl BlockTextureAtlas {
+    /// Builds the atlas out of every image in `folder`, each texture named after its file stem, plus the
+    /// built-in `MISSING_TEXTURE_NAME` texture
+    pub fn from_folder(
+        folder: &LoadedFolder,
+        images: &mut Assets<Image>,
+        max_layers: u32,
+    ) -> Result<Self, BlockTextureAtlasError> {
+        let mut textures = folder
+            .handles
+            .iter()
+            .filter_map(|handle| {
+                let name = handle.path()?.path().file_stem()?.to_string_lossy().into_owned();
+                let image = images.get(&handle.clone().try_typed::<Image>().ok()?)?;
+
+                Some((name, image))
+            })
+            .collect::<Vec<_>>();
+
+        let tile_size = textures.first().ok_or(BlockTextureAtlasError::NoTextures)?.1.width();
+        let missing_texture = missing_texture(tile_size);
+        textures.push((MISSING_TEXTURE_NAME.to_string(), &missing_texture));
+
+        let (image, texture_indices) = stitch_block_textures(textures, max_layers)?;
+
+        Ok(Self {
+            image: images.add(image),
+            texture_indices,
+        })
+    }
+
+    pub fn texture_index(&self, texture_name: &str) -> Option<u32> {
+        self.texture_indices.get(texture_name).copied()
+    }
+}
+
+/// Magenta & black checkerboard of 2x2 squares, hard to mistake for a real texture
+pub fn missing_texture(size: u32) -> Image {
+    let data = (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size, i / size);
+
+            match (x * 2 / size + y * 2 / size) % 2 {
+                0 => [255, 0, 255, 255],
+                _ => [0, 0, 0, 255],
+            }
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD,
+    )
+}
+
+/// Atlas indices of the given textures: ordered by name, so they don't depend on the order textures got loaded in
+pub fn assign_texture_indices(texture_names: impl IntoIterator<Item = String>) -> HashMap<String, u32> {
+    let mut texture_names = texture_names.into_iter().collect::<Vec<_>>();
+    texture_names.sort();
+
+    texture_names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| (name, index as u32))
+        .collect()
+}
+
+/// Stacks same-sized square textures into a 2D texture array, one texture per layer, each with its own
+/// full mip chain. Mips are stored layer by layer, which is the order Bevy uploads texture data in.
+pub fn stitch_block_textures<'a>(
+    textures: impl IntoIterator<Item = (String, &'a Image)>,
+    max_layers: u32,
+) -> Result<(Image, HashMap<String, u32>), BlockTextureAtlasError> {
+    let mut textures_by_name = HashMap::new();
+
+    for (name, texture) in textures {
+        if textures_by_name.insert(name.clone(), texture).is_some() {
+            return Err(BlockTextureAtlasError::DuplicateTexture(name));
+        }
+    }
+
+    let textures = textures_by_name;
+    let texture_indices = assign_texture_indices(textures.keys().cloned());
+
+    if texture_indices.len() > max_layers as usize {
+        return Err(BlockTextureAtlasError::TooManyTextures(texture_indices.len(), max_layers));
+    }
+
+    let tile_size = textures
+        .values()
+        .next()
+        .ok_or(BlockTextureAtlasError::NoTextures)?
+        .width();
+    let mip_level_count = tile_size.ilog2() + 1;
+
+    let mut layers = vec![vec![]; texture_indices.len()];
+
+    for (name, index) in texture_indices.iter() {
+        let texture = textures[name];
+
+        if texture.texture_descriptor.format != TextureFormat::Rgba8UnormSrgb {
+            return Err(BlockTextureAtlasError::UnsupportedFormat(name.clone(), texture.texture_descriptor.format));
+        }
+
+        if texture.width() != tile_size || texture.height() != tile_size {
+            return Err(BlockTextureAtlasError::SizeMismatch {
+                name: name.clone(),
+                width: texture.width(),
+                height: texture.height(),
+                expected: tile_size,
+            });
+        }
+
+        let layer = &mut layers[*index as usize];
+        let mut mip = texture.data.clone();
+        let mut mip_size = tile_size;
+
+        layer.extend_from_slice(&mip);
+
+        for _ in 1..mip_level_count {
+            mip = downsample_srgba(&mip, mip_size);
+            mip_size = (mip_size / 2).max(1);
+            layer.extend_from_slice(&mip);
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: tile_size,
+            height: tile_size,
+            depth_or_array_layers: texture_indices.len() as u32,
+        },
+        TextureDimension::D2,
+        // Base level only, `Image::new` checks the data length against the size without mips
+        layers.iter().flat_map(|layer| &layer[..(tile_size * tile_size * 4) as usize]).copied().collect(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+    );
+
+    image.data = layers.concat();
+    image.texture_descriptor.mip_level_count = mip_level_count;
+    // A single layer would otherwise get a plain 2D view, which doesn't match the material's binding
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+
+    Ok((image, texture_indices))
+}
+
+/// Halves a square sRGB RGBA8 image with a 2x2 box filter, averaging colors in linear space
+fn downsample_srgba(data: &[u8], size: u32) -> Vec<u8> {
+    let half_size = (size / 2).max(1);
+    let mut result = Vec::with_capacity((half_size * half_size * 4) as usize);
+
+    for y in 0..half_size {
+        for x in 0..half_size {
+            let samples = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| {
+                let offset = (((y * 2 + dy).min(size - 1) * size + (x * 2 + dx).min(size - 1)) * 4) as usize;
+                &data[offset..offset + 4]
+            });
+
+            for channel in 0..3 {
+                let linear = samples.iter().map(|sample| srgb_to_linear(sample[channel])).sum::<f32>() / 4.0;
+                result.push(linear_to_srgb(linear));
+            }
+
+            let alpha = samples.iter().map(|sample| sample[3] as f32).sum::<f32>() / 4.0;
+            result.push(alpha.round() as u8);
+        }
+    }
+
+    result
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    };
+
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_texture(size: u32, color: [u8; 4]) -> Image {
+        Image::new_fill(
+            Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &color,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+    }
+
+    fn layer_pixel(image: &Image, layer: u32, x: u32, y: u32) -> [u8; 4] {
+        let size = image.width();
+        let layer_length: u32 = (0..image.texture_descriptor.mip_level_count).map(|level| (size >> level).max(1).pow(2) * 4).sum();
+        let offset = (layer * layer_length + (y * size + x) * 4) as usize;
+
+        image.data[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn textures_are_indexed_by_name_and_stacked_into_layers() {
+        let red = solid_texture(2, [255, 0, 0, 255]);
+        let green = solid_texture(2, [0, 255, 0, 255]);
+        let blue = solid_texture(2, [0, 0, 255, 255]);
+
+        let (atlas, indices) = stitch_block_textures(
+            [
+                ("c".to_string(), &blue),
+                ("a".to_string(), &red),
+                ("b".to_string(), &green),
+            ],
+            256,
+        )
+        .expect("stitch textures");
+
+        assert_eq!(atlas.texture_descriptor.size, Extent3d { width: 2, height: 2, depth_or_array_layers: 3 });
+        assert_eq!(indices["a"], 0);
+        assert_eq!(indices["b"], 1);
+        assert_eq!(indices["c"], 2);
+
+        assert_eq!(layer_pixel(&atlas, 0, 1, 1), [255, 0, 0, 255]);
+        assert_eq!(layer_pixel(&atlas, 1, 0, 0), [0, 255, 0, 255]);
+        assert_eq!(layer_pixel(&atlas, 2, 1, 0), [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn every_layer_gets_a_full_mip_chain() {
+        let mut checkerboard = solid_texture(4, [0, 0, 0, 255]);
+        for (i, pixel) in checkerboard.data.chunks_exact_mut(4).enumerate() {
+            if (i % 4 + i / 4) % 2 == 0 {
+                pixel.copy_from_slice(&[255, 255, 255, 255]);
+            }
+        }
+        let white = solid_texture(4, [255, 255, 255, 255]);
+
+        let (atlas, _) = stitch_block_textures([("a".to_string(), &checkerboard), ("b".to_string(), &white)], 256)
+            .expect("stitch textures");
+
+        // 4x4, 2x2 & 1x1 for each of the two layers
+        assert_eq!(atlas.texture_descriptor.mip_level_count, 3);
+        assert_eq!(atlas.data.len(), 2 * (16 + 4 + 1) * 4);
+
+        let checkerboard_smallest_mip = &atlas.data[20 * 4..21 * 4];
+        let white_smallest_mip = &atlas.data[41 * 4..42 * 4];
+        // Half white & half black in linear space, not sRGB 127
+        assert_eq!(checkerboard_smallest_mip, &[188, 188, 188, 255]);
+        assert_eq!(white_smallest_mip, &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn missing_texture_is_a_checkerboard() {
+        let texture = missing_texture(16);
+        let pixel = |x: u32, y: u32| &texture.data[((y * 16 + x) * 4) as usize..][..4];
+
+        assert_eq!(pixel(0, 0), [255, 0, 255, 255]);
+        assert_eq!(pixel(7, 7), [255, 0, 255, 255]);
+        assert_eq!(pixel(8, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(0, 15), [0, 0, 0, 255]);
+        assert_eq!(pixel(15, 15), [255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn textures_beyond_the_layer_limit_are_rejected() {
+        let texture = solid_texture(1, [0; 4]);
+        let textures = (0..3).map(|i| (i.to_string(), &texture));
+
+        let result = stitch_block_textures(textures, 2);
+
+        assert!(matches!(result, Err(BlockTextureAtlasError::TooManyTextures(3, 2))));
+    }
+
+    #[test]
+    fn mismatched_texture_sizes_are_rejected() {
+        let small = solid_texture(2, [0; 4]);
+        let big = solid_texture(4, [0; 4]);
+
+        let result = stitch_block_textures([("a".to_string(), &small), ("b".to_string(), &big)], 256);
+
+        assert!(matches!(result, Err(BlockTextureAtlasError::SizeMismatch { .. })));
+    }
+}
@@ -0,0 +1,301 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::block_info::BlockSides;
+use crate::block_model::BlockModel;
+use crate::block_state::{BlockProperty, BlockRotation};
+use crate::world::explosion::BlockExplosive;
+
+/// Contents of a `*.blocks.ron` file: every block of a single namespace.
+///
+/// ```ron
+/// #![enable(implicit_some)]
+/// (
+///     namespace: "potato_crust",
+///     blocks: [
+///         (name: "grass", textures: (all: "grass-side", top: "grass-top", bottom: "dirt")),
+///         (name: "poppy", model: Cross, textures: (all: "poppy")),
+///         (name: "lava", textures: (all: "lava"), light_emission: 15, light_tint: (1.0, 0.6, 0.3)),
+///         (name: "tnt", textures: (all: "tnt-side"), explosive: (radius: 4.0, fuse_seconds: 4.0)),
+///         (
+///             name: "log",
+///             properties: [(name: "axis", kind: Enum(["y", "x", "z"]))],
+///             textures: (all: "log-side", top: "log-top", bottom: "log-top"),
+///             states: [
+///                 (when: {"axis": "x"}, rotation: (x: 90, y: 90)),
+///                 (when: {"axis": "z"}, rotation: (x: 90)),
+///             ],
+///         ),
+///     ],
+/// )
+/// ```
+#[derive(Asset, Clone, Debug, Deserialize, TypePath)]
+pub struct BlockDefinitions {
+    pub namespace: String,
+    pub blocks: Vec<BlockDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub is_translucent: bool,
+    #[serde(default)]
+    pub textures: BlockSides,
+    #[serde(default)]
+    pub model: BlockModel,
+    /// Block light level given off, from 0 to 15
+    #[serde(default)]
+    pub light_emission: u8,
+    /// Colour of the light given off as red, green & blue within `0.0..=1.0`, white if left out
+    #[serde(default)]
+    pub light_tint: Option<[f32; 3]>,
+    #[serde(default)]
+    pub explosive: Option<BlockExplosive>,
+    /// Every combination of property values becomes a block state of its own
+    #[serde(default)]
+    pub properties: Vec<BlockProperty>,
+    #[serde(default)]
+    pub states: Vec<BlockStateOverride>,
+}
+
+/// Changes to the states whose property values match `when`. When several overrides match a state,
+/// later ones win.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockStateOverride {
+    #[serde(default)]
+    pub when: HashMap<String, String>,
+    #[serde(default)]
+    pub textures: Option<BlockSides>,
+    #[serde(default)]
+    pub rotation: Option<BlockRotation>,
+}
+
+#[derive(Debug, Error)]
+pub enum BlockDefinitionsLoaderError {
+    #[error("failed to read block definitions: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse block definitions: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct BlockDefinitionsLoader;
+
+impl AssetLoader for BlockDefinitionsLoader {
+    type Asset = BlockDefinitions;
+    type Settings = ();
+    type Error = BlockDefinitionsLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        Ok(BlockDefinitions::from_ron(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["blocks.ron"]
+    }
+}
+
+impl BlockDefinitions {
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_info::{BlockInfoRegistry, BlockSide};
+    use crate::block_state::BlockPropertyValue;
+
+    const DEFINITIONS: &str = r#"
+        #![enable(implicit_some)]
+        (
+            namespace: "test",
+            blocks: [
+                (name: "glass", is_translucent: true, textures: (all: "dirt")),
+                (name: "log", textures: (all: "grass-side", top: "grass-top", bottom: "grass-top")),
+            ],
+        )
+    "#;
+
+    fn texture_index(texture_name: &str) -> Option<u32> {
+        ["dirt", "grass-side", "grass-top"]
+            .iter()
+            .position(|name| *name == texture_name)
+            .map(|index| index as u32)
+    }
+
+    fn parse(definitions: &str) -> BlockDefinitions {
+        BlockDefinitions::from_ron(definitions.as_bytes()).expect("parse block definitions")
+    }
+
+    #[test]
+    fn definitions_register_blocks_with_resolved_textures() {
+        let mut registry = BlockInfoRegistry::default();
+        registry
+            .register_definitions(&parse(DEFINITIONS), texture_index)
+            .expect("register block definitions");
+
+        let glass = registry.get_block_info("test:glass");
+        assert!(glass.is_translucent);
+        assert_eq!(glass.get_side_texture_id(BlockSide::Left), texture_index("dirt"));
+
+        let log = registry.get_block_info("test:log");
+        assert!(!log.is_translucent);
+        assert_eq!(log.get_side_texture_id(BlockSide::Top), texture_index("grass-top"));
+        assert_eq!(log.get_side_texture_id(BlockSide::Front), texture_index("grass-side"));
+    }
+
+    #[test]
+    fn duplicate_block_names_are_rejected() {
+        let mut registry = BlockInfoRegistry::default();
+        registry
+            .register_definitions(&parse(DEFINITIONS), texture_index)
+            .expect("register block definitions");
+
+        let error = registry
+            .register_definitions(&parse(DEFINITIONS), texture_index)
+            .expect_err("same blocks registered twice");
+        assert!(error.to_string().contains("`test:glass` already exists"), "{error}");
+    }
+
+    #[test]
+    fn unknown_textures_are_rejected() {
+        let mut registry = BlockInfoRegistry::default();
+        let definitions = parse(r#"(namespace: "test", blocks: [(name: "odd", textures: (top: Some("no-such-texture")))])"#);
+
+        let error = registry
+            .register_definitions(&definitions, texture_index)
+            .expect_err("unknown texture accepted");
+        assert!(error.to_string().contains("`no-such-texture`"), "{error}");
+    }
+
+    const LOG_DEFINITIONS: &str = r#"
+        #![enable(implicit_some)]
+        (
+            namespace: "test",
+            blocks: [
+                (
+                    name: "log",
+                    properties: [(name: "axis", kind: Enum(["y", "x", "z"])), (name: "mossy", kind: Bool)],
+                    textures: (all: "grass-side", top: "grass-top", bottom: "grass-top"),
+                    states: [
+                        (when: {"axis": "x"}, rotation: (x: 90, y: 90)),
+                        (when: {"mossy": "true"}, textures: (all: "dirt")),
+                    ],
+                ),
+                (name: "glass", textures: (all: "dirt")),
+            ],
+        )
+    "#;
+
+    #[test]
+    fn every_state_is_registered_with_its_own_id() {
+        let mut registry = BlockInfoRegistry::default();
+        registry
+            .register_definitions(&parse(LOG_DEFINITIONS), texture_index)
+            .expect("register block definitions");
+
+        let default_log = registry.get_block_info("test:log");
+        assert_eq!(default_log.get_state_name(), "test:log[axis=y,mossy=false]");
+        assert_eq!(*default_log, *registry.get_block_info("test:log[axis=y,mossy=false]"));
+
+        let log_ids = ["y", "x", "z"]
+            .iter()
+            .flat_map(|axis| ["false", "true"].map(|mossy| registry.get_block_info(format!("test:log[axis={axis},mossy={mossy}]")).id))
+            .collect::<Vec<_>>();
+        assert_eq!(log_ids, (2..8).collect::<Vec<_>>());
+        assert_eq!(registry.get_block_info("test:glass").id, 8);
+
+        let along_x = registry
+            .try_get_block_state(&default_log, "axis", BlockPropertyValue::Enum("x".to_string()))
+            .expect("log has an x axis state");
+        assert_ne!(*along_x, *default_log);
+        assert!(registry
+            .try_get_block_state(&default_log, "facing", BlockPropertyValue::Bool(true))
+            .is_err());
+    }
+
+    #[test]
+    fn states_apply_matching_overrides() {
+        let mut registry = BlockInfoRegistry::default();
+        registry
+            .register_definitions(&parse(LOG_DEFINITIONS), texture_index)
+            .expect("register block definitions");
+
+        // Rotated log shows its rings on the left & right sides
+        let along_x = registry.get_block_info("test:log[axis=x,mossy=false]");
+        assert_eq!(along_x.get_side_texture_id(BlockSide::Right), texture_index("grass-top"));
+        assert_eq!(along_x.get_side_texture_id(BlockSide::Left), texture_index("grass-top"));
+        assert_eq!(along_x.get_side_texture_id(BlockSide::Top), texture_index("grass-side"));
+
+        // Both overrides apply to a mossy log along X
+        let mossy_along_x = registry.get_block_info("test:log[axis=x,mossy=true]");
+        assert_eq!(mossy_along_x.rotation, along_x.rotation);
+        assert_eq!(mossy_along_x.get_side_texture_id(BlockSide::Right), texture_index("dirt"));
+    }
+
+    #[test]
+    fn overrides_of_unknown_properties_are_rejected() {
+        for (states, expected) in [
+            (r#"[(when: {"facing": "x"}, rotation: (y: 90))]"#, "unknown property `facing`"),
+            (r#"[(when: {"axis": "w"}, rotation: (y: 90))]"#, "unknown value `w`"),
+            (r#"[(when: {"axis": "x"}, rotation: (y: 45))]"#, "steps of 90 degrees"),
+        ] {
+            let definitions = parse(&format!(
+                r#"#![enable(implicit_some)] (namespace: "test", blocks: [(name: "log", properties: [(name: "axis", kind: Enum(["y", "x"]))], states: {states})])"#
+            ));
+
+            let error = BlockInfoRegistry::default()
+                .register_definitions(&definitions, texture_index)
+                .expect_err("invalid state override accepted");
+            assert!(error.to_string().contains(expected), "{error}");
+        }
+    }
+
+    #[test]
+    fn invalid_definitions_register_none_of_their_states() {
+        let definitions = parse(
+            r#"#![enable(implicit_some)] (namespace: "test", blocks: [
+                (name: "log", properties: [(name: "mossy", kind: Bool)], textures: (all: "dirt"),
+                    states: [(when: {"mossy": "true"}, textures: (all: "no-such-texture"))]),
+                (name: "glass", textures: (all: "dirt")),
+            ])"#,
+        );
+        let mut registry = BlockInfoRegistry::default();
+
+        let results = definitions
+            .blocks
+            .iter()
+            .map(|definition| registry.register_definition(&definitions.namespace, definition, texture_index).is_ok())
+            .collect::<Vec<_>>();
+
+        // Only the mossy state's texture is missing, yet the plain log isn't left behind either
+        assert_eq!(results, [false, true]);
+        assert!(registry.try_get_block_info("test:log[mossy=false]").is_err());
+        assert!(registry.try_get_block_info("test:log").is_err());
+        assert_eq!(registry.get_block_info("test:glass").id, 2);
+    }
+
+    #[test]
+    fn shipped_definitions_are_valid() {
+        let registry = BlockInfoRegistry::for_tests();
+
+        for name in ["potato_crust:dirt", "potato_crust:grass", "potato_crust:cobblestone", "potato_crust:log[axis=z]"] {
+            assert!(registry.get_block_info(name).get_side_texture_id(BlockSide::Top).is_some(), "{name} has no texture");
+        }
+    }
+}
@@ -1,46 +1,24 @@
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
+use bevy::asset::LoadedFolder;
 use bevy::prelude::*;
 use color_eyre::eyre::eyre;
 use dashmap::DashMap;
 use fasthash::city;
+use serde::Deserialize;
 use strum::IntoEnumIterator;
-
-use crate::assets::{
-    AppState, ATLAS_TEXTURE_COBBLESTONE, ATLAS_TEXTURE_DIRT, ATLAS_TEXTURE_GRASS_SIDE,
-    ATLAS_TEXTURE_GRASS_TOP,
-};
-
-pub const BLOCK_DIRT: BlockInfo = BlockInfo {
-    category: None,
-    is_translucent: false,
-    name: "dirt",
-    sides: BlockSides::new_for_all(ATLAS_TEXTURE_DIRT),
-};
-
-pub const BLOCK_GRASS: BlockInfo = BlockInfo {
-    category: None,
-    is_translucent: false,
-    name: "grass",
-    sides: BlockSides {
-        all: Some(ATLAS_TEXTURE_GRASS_SIDE),
-        top: Some(ATLAS_TEXTURE_GRASS_TOP),
-        bottom: Some(ATLAS_TEXTURE_DIRT),
-        left: None,
-        right: None,
-        front: None,
-        back: None,
-    },
-};
-
-pub const BLOCK_COBBLESTONE: BlockInfo = BlockInfo {
-    category: None,
-    is_translucent: false,
-    name: "cobblestone",
-    sides: BlockSides::new_for_all(ATLAS_TEXTURE_COBBLESTONE),
-};
-
-#[derive(strum::EnumIter)]
+use thiserror::Error;
+
+use crate::assets::{check_loaded_assets, AppState, BlockDefinitionsFolder};
+use crate::atlas::{BlockTextureAtlas, MISSING_TEXTURE_NAME};
+use crate::block_definition::{BlockDefinition, BlockDefinitions, BlockDefinitionsLoader};
+use crate::block_model::BlockModel;
+use crate::block_state::{BlockPropertyValue, BlockRotation, BlockState};
+use crate::world::chunk::CHUNK_SIZE;
+use crate::world::explosion::BlockExplosive;
+use crate::world::light::MAX_LIGHT_LEVEL;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, strum::EnumIter)]
 pub enum BlockSide {
     Front = 0,
     Back = 1,
@@ -51,21 +29,23 @@ pub enum BlockSide {
 }
 
 impl BlockSide {
+    pub const fn normal(&self) -> IVec3 {
+        match self {
+            BlockSide::Front => IVec3::Z,
+            BlockSide::Back => IVec3::NEG_Z,
+            BlockSide::Left => IVec3::NEG_X,
+            BlockSide::Right => IVec3::X,
+            BlockSide::Top => IVec3::Y,
+            BlockSide::Bottom => IVec3::NEG_Y,
+        }
+    }
+
     pub fn match_normal_vector(normal: Vec3) -> BlockSide {
         let mut best_side = None;
         let mut best_dot = 0.0;
 
         for side in BlockSide::iter() {
-            let side_normal = match side {
-                BlockSide::Front => Vec3::Z,
-                BlockSide::Back => -Vec3::Z,
-                BlockSide::Left => -Vec3::X,
-                BlockSide::Right => Vec3::X,
-                BlockSide::Top => Vec3::Y,
-                BlockSide::Bottom => -Vec3::Y,
-            };
-
-            let dot = normal.dot(side_normal);
+            let dot = normal.dot(side.normal().as_vec3());
 
             if dot > best_dot {
                 best_side = Some(side);
@@ -77,113 +57,60 @@ impl BlockSide {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+/// Texture names per block side, `all` is used for every side without its own texture
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
 pub struct BlockSides {
-    pub all: Option<u32>,
-    pub front: Option<u32>,
-    pub back: Option<u32>,
-    pub left: Option<u32>,
-    pub right: Option<u32>,
-    pub top: Option<u32>,
-    pub bottom: Option<u32>,
+    pub all: Option<String>,
+    pub front: Option<String>,
+    pub back: Option<String>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub top: Option<String>,
+    pub bottom: Option<String>,
 }
 
 impl BlockSides {
-    pub fn get_side_texture_id(&self, side: BlockSide) -> Option<u32> {
+    pub fn get_side_texture_name(&self, side: BlockSide) -> Option<&str> {
         match side {
-            BlockSide::Front => self.front.or(self.all),
-            BlockSide::Back => self.back.or(self.all),
-            BlockSide::Left => self.left.or(self.all),
-            BlockSide::Right => self.right.or(self.all),
-            BlockSide::Top => self.top.or(self.all),
-            BlockSide::Bottom => self.bottom.or(self.all),
+            BlockSide::Front => self.front.as_ref().or(self.all.as_ref()),
+            BlockSide::Back => self.back.as_ref().or(self.all.as_ref()),
+            BlockSide::Left => self.left.as_ref().or(self.all.as_ref()),
+            BlockSide::Right => self.right.as_ref().or(self.all.as_ref()),
+            BlockSide::Top => self.top.as_ref().or(self.all.as_ref()),
+            BlockSide::Bottom => self.bottom.as_ref().or(self.all.as_ref()),
         }
+        .map(String::as_str)
     }
 }
 
-impl BlockSides {
-    pub const fn new_for_all(texture_id: u32) -> Self {
-        Self {
-            all: Some(texture_id),
-            front: None,
-            back: None,
-            left: None,
-            right: None,
-            top: None,
-            bottom: None,
-        }
-    }
+/// Dense runtime ID of a registered block, handed out in registration order. Only valid for the registry
+/// that assigned it, anything persisted should go through a `BlockIdMapping` instead.
+pub type BlockId = u16;
 
-    pub const fn new_for_side(
-        side: BlockSide,
-        side_texture_id: u32,
-        fallback_texture_id: u32,
-    ) -> Self {
-        match side {
-            BlockSide::Front => Self {
-                all: Some(fallback_texture_id),
-                front: Some(side_texture_id),
-                back: None,
-                left: None,
-                right: None,
-                top: None,
-                bottom: None,
-            },
-            BlockSide::Back => Self {
-                all: Some(fallback_texture_id),
-                front: None,
-                back: Some(side_texture_id),
-                left: None,
-                right: None,
-                top: None,
-                bottom: None,
-            },
-            BlockSide::Left => Self {
-                all: Some(fallback_texture_id),
-                front: None,
-                back: None,
-                left: Some(side_texture_id),
-                right: None,
-                top: None,
-                bottom: None,
-            },
-            BlockSide::Right => Self {
-                all: Some(fallback_texture_id),
-                front: None,
-                back: None,
-                left: None,
-                right: Some(side_texture_id),
-                top: None,
-                bottom: None,
-            },
-            BlockSide::Top => Self {
-                all: Some(fallback_texture_id),
-                front: None,
-                back: None,
-                left: None,
-                right: None,
-                top: Some(side_texture_id),
-                bottom: None,
-            },
-            BlockSide::Bottom => Self {
-                all: Some(fallback_texture_id),
-                front: None,
-                back: None,
-                left: None,
-                right: None,
-                top: None,
-                bottom: Some(side_texture_id),
-            },
-        }
-    }
-}
+pub const AIR_BLOCK_ID: BlockId = 0;
 
-#[derive(Clone, Copy, Debug, Default)]
+/// Built-in block standing in for blocks that can't be found, e.g. ones that got removed since a chunk was saved
+pub const MISSING_BLOCK_NAME: &str = "potato_crust:missing";
+
+#[derive(Clone, Debug, Default)]
 pub struct BlockInfo {
-    pub category: Option<&'static str>,
-    pub name: &'static str,
+    /// Assigned on registration
+    pub id: BlockId,
+    pub category: Option<String>,
+    pub name: String,
+    /// Every state of a block is registered as a block info of its own, with its own ID
+    pub state: BlockState,
     pub is_translucent: bool,
-    pub sides: BlockSides,
+    /// Atlas index of each side's texture before rotation, indexed by `BlockSide`
+    pub side_texture_ids: [Option<u32>; 6],
+    pub rotation: BlockRotation,
+    pub model: BlockModel,
+    /// Level of the block light the block gives off, 0 for blocks that don't
+    pub light_emission: u8,
+    /// Colour of the block light, white if `None`
+    pub light_tint: Option<Color>,
+    pub explosive: Option<BlockExplosive>,
 }
 
 impl BlockInfo {
@@ -194,85 +121,490 @@ impl BlockInfo {
         }
     }
 
-    pub fn get_registry_name_hash(&self) -> u64 {
-        let registry_name = self.get_registry_name();
-        city::hash64(registry_name.as_bytes())
+    /// Registry name plus property values, e.g. `potato_crust:log[axis=x]`. Same as the registry name for
+    /// blocks without properties.
+    pub fn get_state_name(&self) -> String {
+        match self.state.is_empty() {
+            true => self.get_registry_name(),
+            false => format!("{}[{}]", self.get_registry_name(), self.state),
+        }
     }
 
+    /// Texture of the side facing `side` once the block is rotated
     pub fn get_side_texture_id(&self, side: BlockSide) -> Option<u32> {
-        self.sides.get_side_texture_id(side)
+        self.side_texture_ids[self.rotation.model_side(side) as usize]
+    }
+
+    /// Whether the block keeps characters from moving through it: full cubes do, other models don't
+    pub fn is_solid(&self) -> bool {
+        self.model.is_cube()
+    }
+
+    /// Block light given off in the red, green & blue channel: the emission level scaled by the tint
+    pub fn get_emitted_light(&self) -> [u8; 3] {
+        let tint = self.light_tint.unwrap_or(Color::WHITE).to_srgba();
+
+        [tint.red, tint.green, tint.blue].map(|component| (self.light_emission as f32 * component).round() as u8)
     }
 }
 
 impl PartialEq<Self> for BlockInfo {
     fn eq(&self, other: &Self) -> bool {
-        self.get_registry_name() == other.get_registry_name()
+        self.get_registry_name() == other.get_registry_name() && self.state == other.state
     }
 }
 
 impl Eq for BlockInfo {}
 
-#[derive(Clone, Default, Resource)]
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum BlockInfoRegistryError {
+    #[error("block info with name `{0}` not found")]
+    UnknownName(String),
+    #[error("block info with ID {0} not found")]
+    UnknownId(BlockId),
+    #[error("block `{0}` has no property `{1}`")]
+    MissingProperty(String, String),
+}
+
+#[derive(Clone, Resource)]
 pub struct BlockInfoRegistry {
     block_map: Arc<DashMap<u64, Arc<BlockInfo>>>,
     reverse_key_map: Arc<DashMap<u64, String>>,
+    /// Block with ID `n` lives at index `n - 1`, air has no entry
+    blocks_by_id: Arc<Vec<Arc<BlockInfo>>>,
+    missing_block: Arc<BlockInfo>,
+    /// Names of saved blocks the registry doesn't know, each standing in for its own placeholder ID right after
+    /// the registered blocks' IDs. Placeholders mesh as the `MISSING_BLOCK_NAME` block, but get saved under their
+    /// original name, so the blocks come back once whatever removed them gets undone.
+    placeholder_names: Arc<RwLock<Vec<String>>>,
+}
+
+impl Default for BlockInfoRegistry {
+    fn default() -> Self {
+        Self::new(None)
+    }
 }
 
 impl BlockInfoRegistry {
-    pub(crate) fn initialize() -> color_eyre::Result<Self> {
-        let mut block_info_registry = BlockInfoRegistry::default();
+    /// Empty registry apart from the `MISSING_BLOCK_NAME` placeholder, which gets the given texture on all sides
+    pub fn new(missing_texture_id: Option<u32>) -> Self {
+        let mut registry = Self {
+            block_map: Default::default(),
+            reverse_key_map: Default::default(),
+            blocks_by_id: Default::default(),
+            missing_block: Default::default(),
+            placeholder_names: Default::default(),
+        };
+
+        let (category, name) = MISSING_BLOCK_NAME.split_once(':').expect("namespaced placeholder name");
+        let missing_block = BlockInfo {
+            name: name.to_string(),
+            side_texture_ids: [missing_texture_id; 6],
+            ..Default::default()
+        };
+
+        registry
+            .register(category, missing_block)
+            .expect("placeholder is the first block of an empty registry");
+        registry.missing_block = registry.get_block_info(MISSING_BLOCK_NAME);
+
+        registry
+    }
 
-        block_info_registry.register("potato_crust", BLOCK_DIRT)?;
-        block_info_registry.register("potato_crust", BLOCK_GRASS)?;
-        block_info_registry.register("potato_crust", BLOCK_COBBLESTONE)?;
+    /// Registry with the block definitions & textures shipped with the game, without going through the asset server
+    pub fn initialize() -> color_eyre::Result<Self> {
+        let definitions = BlockDefinitions::from_ron(include_bytes!("../assets/blocks/potato_crust.blocks.ron"))?;
+
+        let textures_directory = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/textures/blocks");
+        let texture_names = std::fs::read_dir(textures_directory)?
+            .map(|entry| Ok(entry?.path().file_stem().unwrap_or_default().to_string_lossy().into_owned()))
+            .chain([Ok(MISSING_TEXTURE_NAME.to_string())])
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let texture_indices = crate::atlas::assign_texture_indices(texture_names);
+
+        let mut block_info_registry = BlockInfoRegistry::new(texture_indices.get(MISSING_TEXTURE_NAME).copied());
+        block_info_registry.register_definitions(&definitions, |name| texture_indices.get(name).copied())?;
 
         Ok(block_info_registry)
     }
 
+    /// Registry of the shipped blocks for tests to build their chunks from
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        Self::initialize().expect("initialize block info registry")
+    }
+
     pub fn get_block_info(&self, registry_name: impl Into<String>) -> Arc<BlockInfo> {
-        let registry_name = registry_name.into();
+        self.try_get_block_info(&registry_name.into())
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_get_block_info(&self, registry_name: &str) -> Result<Arc<BlockInfo>, BlockInfoRegistryError> {
         let key_hash = city::hash64(registry_name.as_bytes());
 
         self.block_map
             .get(&key_hash)
             .map(|x| x.clone())
-            .expect(format!("block info with name `{}` not found", registry_name).as_str())
+            .ok_or_else(|| BlockInfoRegistryError::UnknownName(registry_name.to_string()))
     }
 
-    pub fn get_block_info_by_hash(&self, key_hash: u64) -> Arc<BlockInfo> {
-        self.block_map
-            .get(&key_hash)
-            .map(|x| x.clone())
-            .expect(format!("block info with hash `{}` not found", key_hash).as_str())
+    /// Block with the given runtime ID, air isn't a block so its ID is an error as well. Placeholder IDs give
+    /// the `MISSING_BLOCK_NAME` placeholder.
+    pub fn try_get_block_info_by_id(&self, id: BlockId) -> Result<&Arc<BlockInfo>, BlockInfoRegistryError> {
+        let index = id.checked_sub(1).ok_or(BlockInfoRegistryError::UnknownId(id))? as usize;
+
+        match self.blocks_by_id.get(index) {
+            Some(block_info) => Ok(block_info),
+            None if self.placeholder_name(id).is_some() => Ok(&self.missing_block),
+            None => Err(BlockInfoRegistryError::UnknownId(id)),
+        }
+    }
+
+    /// Placeholder ID of a saved block the registry doesn't know, assigning a new one the first time the name
+    /// comes up. Falls back to the `MISSING_BLOCK_NAME` placeholder's own ID once all IDs are taken.
+    pub fn placeholder_block_id(&self, state_name: &str) -> BlockId {
+        let first_id = self.blocks_by_id.len() + 1;
+        let mut placeholder_names = self.placeholder_names.write().expect("placeholder names aren't poisoned");
+
+        let index = match placeholder_names.iter().position(|name| name == state_name) {
+            Some(index) => index,
+            None => {
+                warn!("Block `{}` not found, substituting `{}` until it's back", state_name, MISSING_BLOCK_NAME);
+                placeholder_names.push(state_name.to_string());
+                placeholder_names.len() - 1
+            }
+        };
+
+        BlockId::try_from(first_id + index).unwrap_or(self.missing_block.id)
+    }
+
+    /// Name a placeholder ID stands in for, `None` for IDs that aren't placeholders
+    pub fn placeholder_name(&self, id: BlockId) -> Option<String> {
+        let index = (id as usize).checked_sub(self.blocks_by_id.len() + 1)?;
+
+        self.placeholder_names.read().expect("placeholder names aren't poisoned").get(index).cloned()
+    }
+
+    /// State name to save the block with the given runtime ID under, `None` for air. Placeholders keep the name
+    /// they were loaded with.
+    pub fn block_id_state_name(&self, id: BlockId) -> Option<String> {
+        self.placeholder_name(id)
+            .or_else(|| self.resolve_block_id(id).map(|block_info| block_info.get_state_name()))
+    }
+
+    /// Block with the given runtime ID as stored in chunks: `None` for air, the `MISSING_BLOCK_NAME` placeholder
+    /// for IDs that don't resolve
+    pub fn resolve_block_id(&self, id: BlockId) -> Option<&Arc<BlockInfo>> {
+        match id {
+            AIR_BLOCK_ID => None,
+            _ => Some(self.try_get_block_info_by_id(id).unwrap_or(&self.missing_block)),
+        }
+    }
+
+    /// Same block as `block_info` in the state with `property` set to `value`
+    pub fn try_get_block_state(
+        &self,
+        block_info: &BlockInfo,
+        property: &str,
+        value: BlockPropertyValue,
+    ) -> Result<Arc<BlockInfo>, BlockInfoRegistryError> {
+        let state = block_info
+            .state
+            .with(property, value)
+            .ok_or_else(|| BlockInfoRegistryError::MissingProperty(block_info.get_registry_name(), property.to_string()))?;
+
+        self.try_get_block_info(&format!("{}[{}]", block_info.get_registry_name(), state))
     }
 
+    /// The `MISSING_BLOCK_NAME` placeholder, to substitute for blocks that failed to resolve
+    pub fn missing_block_info(&self) -> Arc<BlockInfo> {
+        self.missing_block.clone()
+    }
+
+    /// Registers a single block state. The first registered state of a block is its default state,
+    /// which lookups by plain registry name return.
     pub fn register(
         &mut self,
-        category: &'static str,
+        category: &str,
         mut block_info: BlockInfo,
     ) -> color_eyre::Result<String> {
-        let registry_name = format!("{}:{}", category, &block_info.name);
-        let key_hash = city::hash64(registry_name.as_bytes());
+        block_info.category = Some(category.to_string());
 
-        if self.block_map.contains_key(&key_hash) {
+        let registry_name = block_info.get_registry_name();
+        let state_name = block_info.get_state_name();
+        let key_hash = city::hash64(state_name.as_bytes());
+        let default_state_key_hash = city::hash64(registry_name.as_bytes());
+
+        // Placeholders take the IDs right after the registered blocks
+        if !self.placeholder_names.read().expect("placeholder names aren't poisoned").is_empty() {
+            return Err(eyre!(
+                "BlockInfoRegistry::register: can't register `{}` once saved blocks got loaded",
+                state_name
+            ));
+        }
+
+        if self.block_map.contains_key(&key_hash) || (block_info.state.is_empty() && self.block_map.contains_key(&default_state_key_hash)) {
             return Err(eyre!(
                 "BlockInfoRegistry::register: block info with name `{}` already exists",
-                registry_name
+                state_name
+            ));
+        }
+
+        block_info.id = BlockId::try_from(self.blocks_by_id.len() + 1).map_err(|_| {
+            eyre!(
+                "BlockInfoRegistry::register: can't register `{}`, all {} block IDs are taken",
+                state_name,
+                BlockId::MAX
+            )
+        })?;
+
+        let block_info = Arc::new(block_info);
+
+        Arc::make_mut(&mut self.blocks_by_id).push(block_info.clone());
+        self.block_map.insert(key_hash, block_info.clone());
+        self.block_map.entry(default_state_key_hash).or_insert(block_info);
+        self.reverse_key_map.insert(key_hash, state_name.clone());
+
+        Ok(state_name)
+    }
+
+    /// Registers every state of every block of a definitions file, resolving texture names through `texture_index`.
+    /// Stops at the first invalid definition.
+    pub fn register_definitions(
+        &mut self,
+        definitions: &BlockDefinitions,
+        texture_index: impl Fn(&str) -> Option<u32>,
+    ) -> color_eyre::Result<()> {
+        for definition in definitions.blocks.iter() {
+            self.register_definition(&definitions.namespace, definition, &texture_index)?;
+        }
+
+        Ok(())
+    }
+
+    /// Registers every state of a single block definition. Either all of its states get registered or, when the
+    /// definition is invalid, none of them.
+    pub fn register_definition(
+        &mut self,
+        namespace: &str,
+        definition: &BlockDefinition,
+        texture_index: impl Fn(&str) -> Option<u32>,
+    ) -> color_eyre::Result<()> {
+        let block_name = format!("{}:{}", namespace, definition.name);
+
+        validate_definition(&block_name, definition)?;
+
+        // Every state of a block shares its registry name, so any of them existing already makes it a duplicate
+        if self.try_get_block_info(&block_name).is_ok() {
+            return Err(eyre!("BlockInfoRegistry::register: block info with name `{}` already exists", block_name));
+        }
+
+        let block_infos = BlockState::enumerate(&definition.properties)
+            .into_iter()
+            .map(|state| {
+                let mut textures = &definition.textures;
+                let mut rotation = BlockRotation::default();
+
+                for state_override in definition.states.iter().filter(|state_override| state.matches(&state_override.when)) {
+                    textures = state_override.textures.as_ref().unwrap_or(textures);
+                    rotation = state_override.rotation.unwrap_or(rotation);
+                }
+
+                let mut side_texture_ids = [None; 6];
+
+                for side in BlockSide::iter() {
+                    let Some(texture_name) = textures.get_side_texture_name(side) else {
+                        continue;
+                    };
+
+                    side_texture_ids[side as usize] = Some(texture_index(texture_name).ok_or_else(|| {
+                        eyre!("block `{}` refers to unknown texture `{}`", block_name, texture_name)
+                    })?);
+                }
+
+                Ok(BlockInfo {
+                    id: AIR_BLOCK_ID,
+                    category: None,
+                    name: definition.name.clone(),
+                    state,
+                    is_translucent: definition.is_translucent,
+                    model: definition.model.clone(),
+                    light_emission: definition.light_emission,
+                    light_tint: definition.light_tint.map(|[red, green, blue]| Color::srgb(red, green, blue)),
+                    explosive: definition.explosive,
+                    side_texture_ids,
+                    rotation,
+                })
+            })
+            .collect::<color_eyre::Result<Vec<_>>>()?;
+
+        if self.blocks_by_id.len() + block_infos.len() > BlockId::MAX as usize {
+            return Err(eyre!(
+                "BlockInfoRegistry::register: can't register the {} states of `{}`, all {} block IDs are taken",
+                block_infos.len(),
+                block_name,
+                BlockId::MAX
             ));
         }
 
-        block_info.category = Some(category);
+        for block_info in block_infos {
+            self.register(namespace, block_info)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Catches definitions that would misbehave instead of failing: boxes sticking out of the block, or overrides
+/// that would silently never apply because they name properties or values the block doesn't have
+fn validate_definition(block_name: &str, definition: &BlockDefinition) -> color_eyre::Result<()> {
+    if !definition.model.is_valid() {
+        return Err(eyre!("block `{}` has a model with boxes reaching outside of the block", block_name));
+    }
+
+    if definition.light_emission > MAX_LIGHT_LEVEL {
+        return Err(eyre!(
+            "block `{}` emits light level {}, above the maximum of {}",
+            block_name,
+            definition.light_emission,
+            MAX_LIGHT_LEVEL
+        ));
+    }
+
+    if definition.light_tint.is_some_and(|tint| tint.iter().any(|component| !(0.0..=1.0).contains(component))) {
+        return Err(eyre!("block `{}` has a light tint outside of 0.0 to 1.0", block_name));
+    }
+
+    if definition.explosive.is_some_and(|explosive| !explosive.is_valid()) {
+        return Err(eyre!(
+            "block `{}` has to explode with a radius of up to {} blocks, after a fuse that isn't negative",
+            block_name,
+            CHUNK_SIZE
+        ));
+    }
+
+    for (i, property) in definition.properties.iter().enumerate() {
+        if definition.properties[..i].iter().any(|other| other.name == property.name) {
+            return Err(eyre!("block `{}` declares property `{}` more than once", block_name, property.name));
+        }
+    }
+
+    for state_override in definition.states.iter() {
+        for (property_name, value) in state_override.when.iter() {
+            let property = definition
+                .properties
+                .iter()
+                .find(|property| property.name == *property_name)
+                .ok_or_else(|| eyre!("block `{}` has a state override for unknown property `{}`", block_name, property_name))?;
+
+            if !property.kind.values().iter().any(|property_value| property_value.to_string() == *value) {
+                return Err(eyre!(
+                    "block `{}` has a state override for unknown value `{}` of property `{}`",
+                    block_name,
+                    value,
+                    property_name
+                ));
+            }
+        }
+
+        if state_override.rotation.is_some_and(|rotation| !rotation.is_valid()) {
+            return Err(eyre!("block `{}` has a rotation that isn't in steps of 90 degrees", block_name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Name-to-ID table that gets stored alongside block IDs, e.g. in saves, so they can be read back after the
+/// registry changed. IDs are never reassigned, new names only get appended, with `AIR_BLOCK_ID` kept for air.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BlockIdMapping {
+    /// Name of the block with ID `n` at index `n - 1`
+    names: Vec<String>,
+}
 
-        self.block_map.insert(key_hash, Arc::new(block_info));
-        self.reverse_key_map.insert(key_hash, registry_name.clone());
+impl BlockIdMapping {
+    pub fn from_names(names: Vec<String>) -> Self {
+        Self { names }
+    }
 
-        Ok(registry_name)
+    /// Every mapped name, in ID order starting with ID 1
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// ID of the block, mapping it to a new ID if it isn't in the table yet. `None` once all IDs are taken.
+    pub fn get_or_insert(&mut self, registry_name: &str) -> Option<BlockId> {
+        let index = match self.names.iter().position(|name| name == registry_name) {
+            Some(index) => index,
+            None => {
+                self.names.push(registry_name.to_string());
+                self.names.len() - 1
+            }
+        };
+
+        BlockId::try_from(index + 1).ok()
+    }
+
+    /// Looks every mapped name up in `registry`, giving the runtime ID of each, indexed by mapped ID. Names the
+    /// registry doesn't know resolve to placeholder IDs, see `BlockInfoRegistry::placeholder_block_id`.
+    pub fn resolve(&self, registry: &BlockInfoRegistry) -> Vec<BlockId> {
+        let blocks = self.names.iter().map(|name| {
+            // A block that lost the property a state was saved with falls back to its default state
+            let default_state_name = name.split_once('[').map_or(name.as_str(), |(registry_name, _)| registry_name);
+
+            registry
+                .try_get_block_info(name)
+                .or_else(|_| registry.try_get_block_info(default_state_name))
+                .map_or_else(|_| registry.placeholder_block_id(name), |block_info| block_info.id)
+        });
+
+        std::iter::once(AIR_BLOCK_ID).chain(blocks).collect()
     }
 }
 
-pub fn initialize_block_info_registry(mut commands: Commands) {
-    let block_info_registry =
-        BlockInfoRegistry::initialize().expect("Failed to initialize block info registry");
+/// Builds the registry from the loaded block definition files while still in `AppState::LoadingAssets`. Runs once
+/// `check_loaded_assets` has seen the definitions folder finish loading & stitched the texture atlas the definitions'
+/// textures resolve against. Invalid definitions get logged & skipped, leaving the rest of their file registered.
+pub fn initialize_block_info_registry(
+    mut commands: Commands,
+    block_definitions_folder: Res<BlockDefinitionsFolder>,
+    block_texture_atlas: Res<BlockTextureAtlas>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    block_definitions: Res<Assets<BlockDefinitions>>,
+) {
+    let mut block_info_registry = BlockInfoRegistry::new(block_texture_atlas.texture_index(MISSING_TEXTURE_NAME));
+
+    let Some(folder) = loaded_folders.get(&block_definitions_folder.0) else {
+        error!("Block definitions folder isn't loaded, only the missing block placeholder is available");
+        commands.insert_resource(block_info_registry);
+        return;
+    };
+
+    // Block IDs follow registration order, so keep it independent of the order files finished loading in
+    let mut handles = folder.handles.iter().collect::<Vec<_>>();
+    handles.sort_by_key(|handle| handle.path().map(|path| path.to_string()));
+
+    for handle in handles {
+        let Some(definitions) = handle
+            .clone()
+            .try_typed::<BlockDefinitions>()
+            .ok()
+            .and_then(|handle| block_definitions.get(&handle))
+        else {
+            continue;
+        };
+
+        for definition in definitions.blocks.iter() {
+            let result = block_info_registry.register_definition(&definitions.namespace, definition, |name| {
+                block_texture_atlas.texture_index(name)
+            });
+
+            if let Err(error) = result {
+                error!("Skipping block definition `{}` from {:?}: {}", definition.name, handle.path(), error);
+            }
+        }
+    }
 
     commands.insert_resource(block_info_registry);
 }
@@ -282,9 +614,105 @@ pub struct BlockInfoPlugin;
 
 impl Plugin for BlockInfoPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<BlockInfoRegistry>().add_systems(
-            OnEnter(AppState::LoadingAssets),
-            initialize_block_info_registry,
+        app.init_resource::<BlockInfoRegistry>()
+            .init_asset::<BlockDefinitions>()
+            .init_asset_loader::<BlockDefinitionsLoader>()
+            .add_systems(
+                Update,
+                initialize_block_info_registry
+                    .after(check_loaded_assets)
+                    .run_if(in_state(AppState::LoadingAssets).and_then(resource_added::<BlockTextureAtlas>)),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_ids_are_dense_and_skip_air() {
+        let registry = BlockInfoRegistry::for_tests();
+        let mut ids = ["potato_crust:dirt", "potato_crust:grass", "potato_crust:cobblestone"]
+            .map(|name| registry.get_block_info(name).id);
+        ids.sort();
+
+        // The placeholder always comes first
+        assert_eq!(registry.missing_block_info().id, 1);
+        assert_eq!(ids, [2, 3, 4]);
+
+        for id in ids {
+            let block_info = registry.try_get_block_info_by_id(id).expect("block with ID");
+
+            assert_eq!(block_info.id, id);
+            assert!(Arc::ptr_eq(block_info, &registry.get_block_info(block_info.get_registry_name())));
+        }
+
+        assert_eq!(registry.resolve_block_id(AIR_BLOCK_ID), None);
+        assert_eq!(registry.resolve_block_id(BlockId::MAX).map(|block_info| block_info.id), Some(registry.missing_block_info().id));
+    }
+
+    #[test]
+    fn unknown_blocks_are_errors_instead_of_panics() {
+        let registry = BlockInfoRegistry::for_tests();
+
+        assert_eq!(
+            registry.try_get_block_info("test:nope").err(),
+            Some(BlockInfoRegistryError::UnknownName("test:nope".to_string()))
         );
+        assert_eq!(registry.try_get_block_info_by_id(AIR_BLOCK_ID).err(), Some(BlockInfoRegistryError::UnknownId(AIR_BLOCK_ID)));
+        assert_eq!(registry.try_get_block_info_by_id(BlockId::MAX).err(), Some(BlockInfoRegistryError::UnknownId(BlockId::MAX)));
+
+        let missing = registry.missing_block_info();
+        assert_eq!(missing.get_registry_name(), MISSING_BLOCK_NAME);
+        assert!(missing.get_side_texture_id(BlockSide::Top).is_some(), "placeholder has no texture");
+    }
+
+    #[test]
+    fn id_mappings_only_ever_append() {
+        let mut mapping = BlockIdMapping::default();
+
+        assert_eq!(mapping.get_or_insert("test:b"), Some(1));
+        assert_eq!(mapping.get_or_insert("test:a"), Some(2));
+        assert_eq!(mapping.get_or_insert("test:b"), Some(1));
+
+        assert_eq!(mapping.names(), ["test:b", "test:a"]);
+        assert_eq!(BlockIdMapping::from_names(mapping.names().to_vec()), mapping);
+    }
+
+    #[test]
+    fn id_mappings_resolve_against_the_current_registry() {
+        let registry = BlockInfoRegistry::for_tests();
+        let mapping = BlockIdMapping::from_names(vec![
+            "potato_crust:grass".to_string(),
+            "test:gone".to_string(),
+            "potato_crust:log[axis=x]".to_string(),
+            "potato_crust:log[axis=diagonal]".to_string(),
+        ]);
+
+        let ids = mapping.resolve(&registry);
+        let names = ids
+            .iter()
+            .map(|id| registry.resolve_block_id(*id).map(|block_info| block_info.get_state_name()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            [
+                None,
+                Some("potato_crust:grass".to_string()),
+                Some(MISSING_BLOCK_NAME.to_string()),
+                Some("potato_crust:log[axis=x]".to_string()),
+                // States that no longer exist fall back to the block's default state
+                Some("potato_crust:log[axis=y]".to_string()),
+            ]
+        );
+
+        // Unknown blocks get a placeholder of their own, which keeps their name for saving
+        assert_ne!(ids[2], registry.missing_block_info().id);
+        assert_eq!(mapping.resolve(&registry)[2], ids[2]);
+        assert_eq!(registry.block_id_state_name(ids[2]).as_deref(), Some("test:gone"));
+        assert_eq!(registry.block_id_state_name(ids[1]).as_deref(), Some("potato_crust:grass"));
+        assert_eq!(registry.block_id_state_name(AIR_BLOCK_ID), None);
     }
 }
@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::block_info::BlockSide;
+
+/// Shape of a block. Full cubes get greedy-meshed together with their neighbours, every other model
+/// is emitted block by block and doesn't hide the faces of the blocks around it.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub enum BlockModel {
+    #[default]
+    Cube,
+    /// Two crossed diagonal planes, like flowers & grass tufts, showing the front texture
+    Cross,
+    /// Boxes given in pixels of a 16x16 block, e.g. `Boxes([(from: (7, 0, 7), to: (9, 10, 9))])` for a torch
+    Boxes(Vec<BlockModelBox>),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct BlockModelBox {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+}
+
+/// Single face of a block model, in block-local space where the block spans `0..1`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockModelQuad {
+    /// Counter-clockwise when looking at the quad's front
+    pub positions: [Vec3; 4],
+    pub normal: Vec3,
+    pub tex_coords: [Vec2; 4],
+    /// Side whose texture the quad shows
+    pub side: BlockSide,
+}
+
+impl BlockModel {
+    pub fn is_cube(&self) -> bool {
+        *self == BlockModel::Cube
+    }
+
+    /// Boxes have to stay within the block, so the faces around them can be culled as usual
+    pub fn is_valid(&self) -> bool {
+        match self {
+            BlockModel::Cube | BlockModel::Cross => true,
+            BlockModel::Boxes(boxes) => !boxes.is_empty() && boxes.iter().all(|model_box| {
+                (0..3).all(|axis| 0.0 <= model_box.from[axis] && model_box.from[axis] < model_box.to[axis] && model_box.to[axis] <= 16.0)
+            }),
+        }
+    }
+
+    pub fn quads(&self) -> Vec<BlockModelQuad> {
+        match self {
+            BlockModel::Cube => box_quads(Vec3::ZERO, Vec3::ONE),
+            BlockModel::Cross => cross_quads(),
+            BlockModel::Boxes(boxes) => boxes
+                .iter()
+                .flat_map(|model_box| box_quads(Vec3::from(model_box.from) / 16.0, Vec3::from(model_box.to) / 16.0))
+                .collect(),
+        }
+    }
+}
+
+fn box_quads(min: Vec3, max: Vec3) -> Vec<BlockModelQuad> {
+    BlockSide::iter()
+        .map(|side| {
+            // `u` & `v` span the face with `u × v` pointing out of it, `v` pointing up on the sides
+            let (corner, u, v) = match side {
+                BlockSide::Front => (Vec3::new(min.x, min.y, max.z), Vec3::X, Vec3::Y),
+                BlockSide::Back => (Vec3::new(max.x, min.y, min.z), Vec3::NEG_X, Vec3::Y),
+                BlockSide::Left => (Vec3::new(min.x, min.y, min.z), Vec3::Z, Vec3::Y),
+                BlockSide::Right => (Vec3::new(max.x, min.y, max.z), Vec3::NEG_Z, Vec3::Y),
+                BlockSide::Top => (Vec3::new(min.x, max.y, max.z), Vec3::X, Vec3::NEG_Z),
+                BlockSide::Bottom => (Vec3::new(min.x, min.y, min.z), Vec3::X, Vec3::Z),
+            };
+            let size = max - min;
+            let (u, v) = (u * u.abs().dot(size), v * v.abs().dot(size));
+            let positions = [corner, corner + u, corner + u + v, corner + v];
+
+            BlockModelQuad {
+                positions,
+                normal: side.normal().as_vec3(),
+                // Each face shows the part of the texture it'd cover on a full block
+                tex_coords: positions.map(|position| Vec2::new(texture_offset(position, u), 1.0 - texture_offset(position, v))),
+                side,
+            }
+        })
+        .collect()
+}
+
+// How far along `axis` a block-local position is, measured from the block's edge `axis` starts from
+fn texture_offset(position: Vec3, axis: Vec3) -> f32 {
+    let axis = axis.normalize();
+
+    match axis.min_element() < 0.0 {
+        true => 1.0 + position.dot(axis),
+        false => position.dot(axis),
+    }
+}
+
+fn cross_quads() -> Vec<BlockModelQuad> {
+    let diagonals = [(Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0)), (Vec3::X, Vec3::Z)];
+    let tex_coords = [Vec2::new(0.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 0.0)];
+
+    diagonals
+        .into_iter()
+        .flat_map(|(start, end)| {
+            let positions = [start, end, end + Vec3::Y, start + Vec3::Y];
+            let normal = (end - start).cross(Vec3::Y).normalize();
+
+            // Planes have no back faces, so each one is emitted facing both ways
+            let front = BlockModelQuad {
+                positions,
+                normal,
+                tex_coords,
+                side: BlockSide::Front,
+            };
+            let back = BlockModelQuad {
+                positions: [positions[1], positions[0], positions[3], positions[2]],
+                normal: -normal,
+                tex_coords: [tex_coords[1], tex_coords[0], tex_coords[3], tex_coords[2]],
+                side: BlockSide::Front,
+            };
+
+            [front, back]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn face_normal(quad: &BlockModelQuad) -> Vec3 {
+        let [a, b, c, _] = quad.positions;
+        (b - a).cross(c - a).normalize()
+    }
+
+    #[test]
+    fn quads_face_outwards() {
+        let torch = BlockModel::Boxes(vec![BlockModelBox {
+            from: [7.0, 0.0, 7.0],
+            to: [9.0, 10.0, 9.0],
+        }]);
+
+        for model in [BlockModel::Cube, BlockModel::Cross, torch] {
+            for quad in model.quads() {
+                assert!(face_normal(&quad).abs_diff_eq(quad.normal, 1e-6), "{model:?} has a quad wound against its normal: {quad:?}");
+            }
+        }
+
+        let cube = BlockModel::Cube.quads();
+        for (quad, side) in cube.iter().zip(BlockSide::iter()) {
+            let center = quad.positions.iter().sum::<Vec3>() / 4.0;
+            assert_eq!(center, Vec3::splat(0.5) + side.normal().as_vec3() / 2.0);
+        }
+    }
+
+    #[test]
+    fn boxes_show_the_part_of_the_texture_they_cover() {
+        let torch = BlockModel::Boxes(vec![BlockModelBox {
+            from: [7.0, 0.0, 7.0],
+            to: [9.0, 10.0, 9.0],
+        }]);
+        let quads = torch.quads();
+
+        let front = quads.iter().find(|quad| quad.side == BlockSide::Front).expect("box has a front face");
+        let min = front.tex_coords.iter().fold(Vec2::MAX, |min, uv| min.min(*uv));
+        let max = front.tex_coords.iter().fold(Vec2::MIN, |max, uv| max.max(*uv));
+
+        assert!(min.abs_diff_eq(Vec2::new(7.0, 6.0) / 16.0, 1e-6), "{min}");
+        assert!(max.abs_diff_eq(Vec2::new(9.0, 16.0) / 16.0, 1e-6), "{max}");
+
+        assert!(torch.is_valid());
+        assert!(!BlockModel::Boxes(vec![]).is_valid());
+        assert!(!BlockModel::Boxes(vec![BlockModelBox { from: [0.0; 3], to: [17.0, 1.0, 1.0] }]).is_valid());
+    }
+}
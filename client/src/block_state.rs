@@ -0,0 +1,243 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use crate::block_info::BlockSide;
+
+/// Property a block declares in its definition, e.g. `(name: "axis", kind: Enum(["y", "x", "z"]))`
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockProperty {
+    pub name: String,
+    pub kind: BlockPropertyKind,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub enum BlockPropertyKind {
+    Enum(Vec<String>),
+    Bool,
+    /// Every integer from the first value up to and including the second one
+    Int(u8, u8),
+}
+
+impl BlockPropertyKind {
+    pub fn values(&self) -> Vec<BlockPropertyValue> {
+        match self {
+            Self::Enum(variants) => variants.iter().cloned().map(BlockPropertyValue::Enum).collect(),
+            Self::Bool => vec![BlockPropertyValue::Bool(false), BlockPropertyValue::Bool(true)],
+            Self::Int(min, max) => (*min..=*max).map(BlockPropertyValue::Int).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum BlockPropertyValue {
+    Enum(String),
+    Bool(bool),
+    Int(u8),
+}
+
+impl fmt::Display for BlockPropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Enum(variant) => write!(f, "{}", variant),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Int(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Value of each of a block's properties, in the order the block declares them. Formats as `axis=x,lit=true`,
+/// which is how states are told apart in registry names.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct BlockState(Vec<(String, BlockPropertyValue)>);
+
+impl BlockState {
+    /// Every combination of the properties' values, the first property changing slowest. The first state,
+    /// made of each property's first value, is the block's default state.
+    pub fn enumerate(properties: &[BlockProperty]) -> Vec<BlockState> {
+        properties.iter().fold(vec![BlockState::default()], |states, property| {
+            states
+                .iter()
+                .flat_map(|state| {
+                    property.kind.values().into_iter().map(|value| {
+                        let mut state = state.clone();
+                        state.0.push((property.name.clone(), value));
+                        state
+                    })
+                })
+                .collect()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, property: &str) -> Option<&BlockPropertyValue> {
+        self.0.iter().find(|(name, _)| name == property).map(|(_, value)| value)
+    }
+
+    /// Same state with `property` set to `value`, `None` if the state has no such property
+    pub fn with(&self, property: &str, value: BlockPropertyValue) -> Option<BlockState> {
+        let mut state = self.clone();
+        let (_, current_value) = state.0.iter_mut().find(|(name, _)| name == property)?;
+        *current_value = value;
+
+        Some(state)
+    }
+
+    /// Whether each property named in `conditions` has the given value, written the way it's formatted
+    pub fn matches(&self, conditions: &HashMap<String, String>) -> bool {
+        conditions
+            .iter()
+            .all(|(property, value)| self.get(property).is_some_and(|current| current.to_string() == *value))
+    }
+}
+
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (property, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+
+            write!(f, "{}={}", property, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rotation of a block's model in degrees, multiples of 90: first around the X axis, then around the Y axis.
+/// `(x: 90, y: 90)` turns the model's top towards +X, like a log lying along the X axis.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct BlockRotation {
+    pub x: u16,
+    pub y: u16,
+}
+
+impl BlockRotation {
+    pub fn is_valid(&self) -> bool {
+        self.x.is_multiple_of(90) && self.y.is_multiple_of(90)
+    }
+
+    /// Rotates a vector of the unrotated model, e.g. a model vertex relative to the block's center
+    pub fn rotate(&self, mut vector: Vec3) -> Vec3 {
+        for _ in 0..self.x / 90 % 4 {
+            vector = Vec3::new(vector.x, -vector.z, vector.y);
+        }
+
+        for _ in 0..self.y / 90 % 4 {
+            vector = Vec3::new(vector.z, vector.y, -vector.x);
+        }
+
+        vector
+    }
+
+    /// Side of the unrotated model that ends up facing `side`
+    pub fn model_side(&self, side: BlockSide) -> BlockSide {
+        // Undo the rotation: three quarter turns around an axis are one quarter turn back
+        let mut normal = side.normal();
+
+        for _ in 0..(4 - self.y / 90 % 4) % 4 {
+            normal = IVec3::new(normal.z, normal.y, -normal.x);
+        }
+
+        for _ in 0..(4 - self.x / 90 % 4) % 4 {
+            normal = IVec3::new(normal.x, -normal.z, normal.y);
+        }
+
+        BlockSide::match_normal_vector(normal.as_vec3())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    fn property(name: &str, kind: BlockPropertyKind) -> BlockProperty {
+        BlockProperty {
+            name: name.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn states_enumerate_every_permutation() {
+        let properties = [
+            property("facing", BlockPropertyKind::Enum(vec!["north".to_string(), "south".to_string()])),
+            property("lit", BlockPropertyKind::Bool),
+            property("level", BlockPropertyKind::Int(1, 3)),
+        ];
+
+        let states = BlockState::enumerate(&properties);
+        let names = states.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert_eq!(states.len(), 2 * 2 * 3);
+        assert_eq!(names[0], "facing=north,lit=false,level=1");
+        assert_eq!(names[1], "facing=north,lit=false,level=2");
+        assert_eq!(names[11], "facing=south,lit=true,level=3");
+        assert_eq!(names.iter().collect::<std::collections::HashSet<_>>().len(), names.len());
+
+        assert_eq!(BlockState::enumerate(&[]), vec![BlockState::default()]);
+    }
+
+    #[test]
+    fn states_match_conditions_and_change_single_properties() {
+        let properties = [
+            property("axis", BlockPropertyKind::Enum(vec!["y".to_string(), "x".to_string()])),
+            property("lit", BlockPropertyKind::Bool),
+        ];
+        let state = BlockState::enumerate(&properties).remove(0);
+
+        let conditions = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+        assert!(state.matches(&conditions(&[])));
+        assert!(state.matches(&conditions(&[("axis", "y"), ("lit", "false")])));
+        assert!(!state.matches(&conditions(&[("axis", "x")])));
+        assert!(!state.matches(&conditions(&[("facing", "y")])));
+
+        let changed = state.with("axis", BlockPropertyValue::Enum("x".to_string())).expect("block has an axis");
+        assert_eq!(changed.to_string(), "axis=x,lit=false");
+        assert!(state.with("facing", BlockPropertyValue::Bool(true)).is_none());
+    }
+
+    #[test]
+    fn rotation_remaps_sides() {
+        let identity = BlockRotation::default();
+        for side in BlockSide::iter() {
+            assert_eq!(identity.model_side(side), side);
+        }
+
+        // Log along the X axis: its top & bottom face left and right
+        let along_x = BlockRotation { x: 90, y: 90 };
+        assert_eq!(along_x.model_side(BlockSide::Right), BlockSide::Top);
+        assert_eq!(along_x.model_side(BlockSide::Left), BlockSide::Bottom);
+        assert_eq!(along_x.model_side(BlockSide::Top), BlockSide::Back);
+
+        // Log along the Z axis
+        let along_z = BlockRotation { x: 90, y: 0 };
+        assert_eq!(along_z.model_side(BlockSide::Front), BlockSide::Top);
+        assert_eq!(along_z.model_side(BlockSide::Back), BlockSide::Bottom);
+        assert_eq!(along_z.model_side(BlockSide::Top), BlockSide::Back);
+
+        // Furnace facing +X instead of +Z
+        let facing_x = BlockRotation { x: 0, y: 90 };
+        assert_eq!(facing_x.model_side(BlockSide::Right), BlockSide::Front);
+        assert_eq!(facing_x.model_side(BlockSide::Top), BlockSide::Top);
+
+        // Rotating the model side brings it back to the side it was looked up for
+        for rotation in [along_x, along_z, facing_x, BlockRotation { x: 270, y: 180 }] {
+            for side in BlockSide::iter() {
+                assert_eq!(rotation.rotate(rotation.model_side(side).normal().as_vec3()), side.normal().as_vec3());
+            }
+        }
+
+        assert!(!BlockRotation { x: 45, y: 0 }.is_valid());
+    }
+}
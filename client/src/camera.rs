@@ -24,6 +24,8 @@ pub const RADIANS_PER_DOT: f32 = 1.0 / 180.0;
 #[derive(Component)]
 pub struct CameraController {
     pub enabled: bool,
+    /// Whether keys fly the camera around. Mouse look keeps working without it, e.g. while walking.
+    pub movement_enabled: bool,
     pub initialized: bool,
     pub sensitivity: f32,
     pub key_forward: KeyCode,
@@ -48,6 +50,7 @@ impl Default for CameraController {
     fn default() -> Self {
         Self {
             enabled: true,
+            movement_enabled: true,
             initialized: false,
             sensitivity: 1.0,
             key_forward: KeyCode::KeyW,
@@ -70,6 +73,12 @@ impl Default for CameraController {
     }
 }
 
+impl CameraController {
+    pub fn is_adjusting_speed(&self, key_input: &ButtonInput<KeyCode>) -> bool {
+        self.enabled && self.movement_enabled && key_input.pressed(self.key_run)
+    }
+}
+
 impl fmt::Display for CameraController {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -77,13 +86,14 @@ impl fmt::Display for CameraController {
             "
 Freecam Controls:
     Mouse\t- Move camera orientation
-    Scroll\t- Adjust movement speed
+    {:?} & Scroll\t- Adjust movement speed
     {:?}\t- Hold to grab cursor
     {:?}\t- Toggle cursor grab
     {:?} & {:?}\t- Fly forward & backwards
     {:?} & {:?}\t- Fly sideways left & right
     {:?} & {:?}\t- Fly up & down
     {:?}\t- Fly faster while held",
+            self.key_run,
             self.mouse_key_cursor_grab,
             self.keyboard_key_toggle_cursor_grab,
             self.key_forward,
@@ -132,8 +142,11 @@ fn run_camera_controller(
             };
             scroll += amount;
         }
-        controller.walk_speed += scroll * controller.scroll_factor * controller.walk_speed;
-        controller.run_speed = controller.walk_speed * 3.0;
+        // Scrolling picks hotbar slots unless the run key is held
+        if controller.is_adjusting_speed(&key_input) {
+            controller.walk_speed += scroll * controller.scroll_factor * controller.walk_speed;
+            controller.run_speed = controller.walk_speed * 3.0;
+        }
 
         // Handle key input
         let mut axis_input = Vec3::ZERO;
@@ -155,6 +168,10 @@ fn run_camera_controller(
         if key_input.pressed(controller.key_down) {
             axis_input.y -= 1.0;
         }
+        if !controller.movement_enabled {
+            axis_input = Vec3::ZERO;
+            controller.velocity = Vec3::ZERO;
+        }
 
         let mut cursor_grab_change = false;
         if key_input.just_pressed(controller.keyboard_key_toggle_cursor_grab) {
@@ -0,0 +1,373 @@
+//! A walking controller for the player, colliding with the world's blocks.
+//! Mouse look is left to the [`CameraController`], which stops moving the camera on its own while walking.
+
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::assets::AppState;
+use crate::camera::CameraController;
+use crate::world::chunk::split_block_position;
+use crate::world::chunk_map::WorldBlocks;
+use crate::world::collision::{move_and_collide, sweep_axis, Aabb};
+
+/// How far below the character's feet ground still counts as being stood on
+const GROUND_PROBE_DISTANCE: f32 = 0.05;
+
+pub struct CharacterControllerPlugin;
+
+impl Plugin for CharacterControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (toggle_character_controller, run_character_controller)
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct CharacterController {
+    /// Walking when enabled, flying around with the freecam otherwise
+    pub enabled: bool,
+    pub key_forward: KeyCode,
+    pub key_back: KeyCode,
+    pub key_left: KeyCode,
+    pub key_right: KeyCode,
+    pub key_jump: KeyCode,
+    pub key_sneak: KeyCode,
+    pub key_sprint: KeyCode,
+    pub key_toggle: KeyCode,
+    pub width: f32,
+    pub height: f32,
+    /// Height of the camera above the character's feet
+    pub eye_height: f32,
+    pub sneak_eye_height: f32,
+    pub walk_speed: f32,
+    pub sprint_speed: f32,
+    pub sneak_speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    pub max_fall_speed: f32,
+    /// Highest ledge the character walks onto without jumping
+    pub step_height: f32,
+    pub velocity: Vec3,
+    pub is_on_ground: bool,
+    pub is_sneaking: bool,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_forward: KeyCode::KeyW,
+            key_back: KeyCode::KeyS,
+            key_left: KeyCode::KeyA,
+            key_right: KeyCode::KeyD,
+            key_jump: KeyCode::Space,
+            key_sneak: KeyCode::ShiftLeft,
+            key_sprint: KeyCode::ControlLeft,
+            key_toggle: KeyCode::KeyF,
+            width: 0.6,
+            height: 1.8,
+            eye_height: 1.62,
+            sneak_eye_height: 1.35,
+            walk_speed: 4.3,
+            sprint_speed: 5.6,
+            sneak_speed: 1.3,
+            jump_speed: 9.0,
+            gravity: 32.0,
+            max_fall_speed: 60.0,
+            step_height: 0.6,
+            velocity: Vec3::ZERO,
+            is_on_ground: false,
+            is_sneaking: false,
+        }
+    }
+}
+
+impl fmt::Display for CharacterController {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "
+Walking Controls:
+    Mouse\t- Look around
+    {:?} & {:?}\t- Walk forward & backwards
+    {:?} & {:?}\t- Walk sideways left & right
+    {:?}\t- Jump
+    {:?}\t- Sneak, without falling off ledges
+    {:?}\t- Sprint while held
+    {:?}\t- Toggle between walking & the freecam",
+            self.key_forward,
+            self.key_back,
+            self.key_left,
+            self.key_right,
+            self.key_jump,
+            self.key_sneak,
+            self.key_sprint,
+            self.key_toggle,
+        )
+    }
+}
+
+/// Movement wanted by the player for a single step
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CharacterInput {
+    /// Horizontal world-space direction to walk in, zero to stand still
+    pub direction: Vec3,
+    pub jump: bool,
+    pub sneak: bool,
+    pub sprint: bool,
+}
+
+impl CharacterController {
+    /// Offset of the camera from the character's feet
+    pub fn eye_offset(&self) -> Vec3 {
+        match self.is_sneaking {
+            true => Vec3::Y * self.sneak_eye_height,
+            false => Vec3::Y * self.eye_height,
+        }
+    }
+
+    /// Box the character takes up while standing at `feet_position`
+    pub fn collider(&self, feet_position: Vec3) -> Aabb {
+        Aabb::from_feet(feet_position, self.width, self.height)
+    }
+
+    fn has_ground_below(&self, aabb: &Aabb, is_solid: &impl Fn(IVec3) -> bool) -> bool {
+        sweep_axis(aabb, 1, -GROUND_PROBE_DISTANCE, is_solid) > -GROUND_PROBE_DISTANCE
+    }
+
+    /// Advances the character standing at `feet_position` by `delta_seconds`, returning where its feet end up
+    pub fn step(&mut self, feet_position: Vec3, input: &CharacterInput, delta_seconds: f32, is_solid: &impl Fn(IVec3) -> bool) -> Vec3 {
+        self.is_sneaking = input.sneak;
+
+        let speed = match (input.sneak, input.sprint) {
+            (true, _) => self.sneak_speed,
+            (false, true) => self.sprint_speed,
+            (false, false) => self.walk_speed,
+        };
+        let horizontal_velocity = Vec3::new(input.direction.x, 0.0, input.direction.z).normalize_or_zero() * speed;
+        self.velocity.x = horizontal_velocity.x;
+        self.velocity.z = horizontal_velocity.z;
+
+        if input.jump && self.is_on_ground {
+            self.velocity.y = self.jump_speed;
+        }
+        self.velocity.y = (self.velocity.y - self.gravity * delta_seconds).max(-self.max_fall_speed);
+
+        let aabb = self.collider(feet_position);
+        let mut offset = self.velocity * delta_seconds;
+
+        // Sneaking characters stop at ledges rather than walking off them
+        if self.is_sneaking && self.is_on_ground {
+            let mut accepted_offset = Vec3::ZERO;
+
+            for axis in [0, 2] {
+                let mut axis_offset = accepted_offset;
+                axis_offset[axis] = offset[axis];
+
+                match self.has_ground_below(&aabb.translated(axis_offset), is_solid) {
+                    true => accepted_offset = axis_offset,
+                    false => offset[axis] = 0.0,
+                }
+            }
+        }
+
+        let (mut moved, applied_offset) = move_and_collide(&aabb, offset, is_solid);
+
+        self.is_on_ground = offset.y < 0.0 && applied_offset.y > offset.y;
+        if applied_offset.y != offset.y {
+            self.velocity.y = 0.0;
+        }
+
+        // Walking into a ledge low enough to step onto: retry the move from above it, then settle back down
+        let is_blocked_sideways = applied_offset.x != offset.x || applied_offset.z != offset.z;
+
+        if is_blocked_sideways && self.is_on_ground && self.step_height > 0.0 {
+            let (raised, raise_offset) = move_and_collide(&aabb, Vec3::Y * self.step_height, is_solid);
+            let (stepped, _) = move_and_collide(&raised, Vec3::new(offset.x, 0.0, offset.z), is_solid);
+            let (lowered, _) = move_and_collide(&stepped, Vec3::NEG_Y * raise_offset.y, is_solid);
+
+            let horizontal_distance = |moved: &Aabb| (moved.min - aabb.min).xz().length_squared();
+            if horizontal_distance(&lowered) > horizontal_distance(&moved) {
+                moved = lowered;
+            }
+        }
+
+        moved.feet_position()
+    }
+}
+
+fn toggle_character_controller(
+    key_input: Res<ButtonInput<KeyCode>>,
+    mut query: Query<(&mut CharacterController, &mut CameraController)>,
+) {
+    for (mut character_controller, mut camera_controller) in query.iter_mut() {
+        if !key_input.just_pressed(character_controller.key_toggle) {
+            continue;
+        }
+
+        character_controller.enabled = !character_controller.enabled;
+        character_controller.velocity = Vec3::ZERO;
+        character_controller.is_on_ground = false;
+        camera_controller.movement_enabled = !character_controller.enabled;
+
+        match character_controller.enabled {
+            true => info!("{}", *character_controller),
+            false => info!("{}", *camera_controller),
+        }
+    }
+}
+
+fn run_character_controller(
+    time: Res<Time>,
+    key_input: Res<ButtonInput<KeyCode>>,
+    world_blocks: WorldBlocks,
+    mut query: Query<(&mut Transform, &mut CharacterController, &CameraController)>,
+) {
+    // Chunks that aren't loaded yet count as solid, so the character doesn't fall through the world while it generates
+    let is_solid = |block_position: IVec3| {
+        let (chunk_position, _) = split_block_position(block_position);
+
+        world_blocks.chunk(chunk_position).is_none() || world_blocks.get_block(block_position).is_some_and(|block_info| block_info.is_solid())
+    };
+
+    for (mut transform, mut character_controller, camera_controller) in query.iter_mut() {
+        if !character_controller.enabled {
+            continue;
+        }
+
+        let mut axis_input = Vec2::ZERO;
+        if key_input.pressed(character_controller.key_forward) {
+            axis_input.y += 1.0;
+        }
+        if key_input.pressed(character_controller.key_back) {
+            axis_input.y -= 1.0;
+        }
+        if key_input.pressed(character_controller.key_right) {
+            axis_input.x += 1.0;
+        }
+        if key_input.pressed(character_controller.key_left) {
+            axis_input.x -= 1.0;
+        }
+
+        // Walking follows where the camera looks horizontally, whatever its pitch
+        let yaw = Quat::from_rotation_y(camera_controller.yaw);
+        let input = CharacterInput {
+            direction: yaw * Vec3::NEG_Z * axis_input.y + yaw * Vec3::X * axis_input.x,
+            jump: key_input.pressed(character_controller.key_jump),
+            sneak: key_input.pressed(character_controller.key_sneak),
+            sprint: key_input.pressed(character_controller.key_sprint),
+        };
+
+        let feet_position = transform.translation - character_controller.eye_offset();
+        let feet_position = character_controller.step(feet_position, &input, time.delta_seconds(), &is_solid);
+        transform.translation = feet_position + character_controller.eye_offset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA_SECONDS: f32 = 1.0 / 60.0;
+
+    fn simulate(
+        character_controller: &mut CharacterController,
+        mut feet_position: Vec3,
+        input: &CharacterInput,
+        steps: usize,
+        is_solid: &impl Fn(IVec3) -> bool,
+    ) -> Vec3 {
+        for _ in 0..steps {
+            feet_position = character_controller.step(feet_position, input, DELTA_SECONDS, is_solid);
+        }
+
+        feet_position
+    }
+
+    fn floor(block_position: IVec3) -> bool {
+        block_position.y < 0
+    }
+
+    #[test]
+    fn characters_fall_land_and_jump() {
+        let mut character_controller = CharacterController::default();
+        let landed = simulate(&mut character_controller, Vec3::new(0.5, 5.0, 0.5), &CharacterInput::default(), 60, &floor);
+
+        assert_eq!(landed, Vec3::new(0.5, 0.0, 0.5));
+        assert!(character_controller.is_on_ground);
+
+        let jump = CharacterInput { jump: true, ..Default::default() };
+        let mut feet_position = character_controller.step(landed, &jump, DELTA_SECONDS, &floor);
+        let mut peak_height = feet_position.y;
+
+        while feet_position.y > 0.0 {
+            feet_position = character_controller.step(feet_position, &CharacterInput::default(), DELTA_SECONDS, &floor);
+            peak_height = peak_height.max(feet_position.y);
+        }
+
+        // High enough to jump onto a block, not onto two
+        assert!((1.0..2.0).contains(&peak_height), "jumped {peak_height} blocks high");
+        assert_eq!(feet_position.y, 0.0);
+    }
+
+    #[test]
+    fn characters_step_onto_ledges_but_not_walls() {
+        let walk_east = CharacterInput { direction: Vec3::X, ..Default::default() };
+
+        // Terrain is made of whole blocks, so stepping gets exercised with a step height of a whole block
+        let ledge = |block_position: IVec3| floor(block_position) || block_position == IVec3::new(2, 0, 0);
+        let mut character_controller = CharacterController { step_height: 1.0, ..Default::default() };
+        let feet_position = simulate(&mut character_controller, Vec3::new(0.5, 0.0, 0.5), &walk_east, 30, &ledge);
+
+        assert_eq!(feet_position.y, 1.0);
+        assert!(feet_position.x > 2.0, "stuck at {feet_position}");
+
+        let wall = |block_position: IVec3| floor(block_position) || (block_position.x == 2 && block_position.y < 2);
+        let mut character_controller = CharacterController { step_height: 1.0, ..Default::default() };
+        let feet_position = simulate(&mut character_controller, Vec3::new(0.5, 0.0, 0.5), &walk_east, 30, &wall);
+
+        assert_eq!(feet_position.y, 0.0);
+        assert!((feet_position.x - (2.0 - character_controller.width / 2.0)).abs() < 1e-3, "ended up at {feet_position}");
+    }
+
+    #[test]
+    fn one_block_ledges_take_a_jump() {
+        let ledge = |block_position: IVec3| floor(block_position) || (block_position.x >= 2 && block_position.y == 0);
+        let walk_east = CharacterInput { direction: Vec3::X, ..Default::default() };
+
+        let mut character_controller = CharacterController { is_on_ground: true, ..Default::default() };
+        let feet_position = simulate(&mut character_controller, Vec3::new(0.5, 0.0, 0.5), &walk_east, 30, &ledge);
+
+        assert_eq!(feet_position.y, 0.0);
+        assert!((feet_position.x - (2.0 - character_controller.width / 2.0)).abs() < 1e-3, "ended up at {feet_position}");
+
+        let jump_east = CharacterInput { jump: true, ..walk_east };
+        let feet_position = character_controller.step(feet_position, &jump_east, DELTA_SECONDS, &ledge);
+        let feet_position = simulate(&mut character_controller, feet_position, &walk_east, 60, &ledge);
+
+        assert_eq!(feet_position.y, 1.0);
+        assert!(feet_position.x > 2.0, "stuck at {feet_position}");
+    }
+
+    #[test]
+    fn sneaking_keeps_characters_from_walking_off_ledges() {
+        let platform = |block_position: IVec3| block_position.y < 0 && block_position.x < 1;
+        let sneak_east = CharacterInput { direction: Vec3::X, sneak: true, ..Default::default() };
+
+        let mut character_controller = CharacterController { is_on_ground: true, ..Default::default() };
+        let feet_position = simulate(&mut character_controller, Vec3::new(0.5, 0.0, 0.5), &sneak_east, 120, &platform);
+
+        assert_eq!(feet_position.y, 0.0);
+        assert!(feet_position.x <= 1.0 + character_controller.width / 2.0, "walked off to {feet_position}");
+        assert!(feet_position.x > 1.0, "stopped short at {feet_position}");
+
+        let walk_east = CharacterInput { direction: Vec3::X, ..Default::default() };
+        let feet_position = simulate(&mut character_controller, feet_position, &walk_east, 60, &platform);
+        assert!(feet_position.y < 0.0, "didn't fall off the ledge");
+    }
+}
@@ -0,0 +1,290 @@
+use std::sync::Arc;
+
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+
+use crate::assets::AppState;
+use crate::block_info::{BlockInfo, BlockInfoRegistry};
+use crate::camera::CameraController;
+use crate::player::Player;
+
+pub const HOTBAR_SIZE: usize = 9;
+pub const INVENTORY_SIZE: usize = 4 * HOTBAR_SIZE;
+pub const MAX_STACK_SIZE: u32 = 64;
+
+/// Items the player starts out with, so there's more to place than what can be dug up
+const STARTING_ITEMS: [(&str, u32); 6] = [
+    ("potato_crust:cobblestone", MAX_STACK_SIZE),
+    ("potato_crust:glass", MAX_STACK_SIZE),
+    ("potato_crust:log", MAX_STACK_SIZE),
+    ("potato_crust:torch", MAX_STACK_SIZE),
+    ("potato_crust:lava", MAX_STACK_SIZE),
+    ("potato_crust:tnt", MAX_STACK_SIZE),
+];
+
+const HOTBAR_KEYS: [KeyCode; HOTBAR_SIZE] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), (fill_starting_inventory, spawn_hotbar))
+            .add_systems(Update, (select_hotbar_slot, update_hotbar).chain().run_if(in_state(AppState::InGame)));
+    }
+}
+
+/// Some amount of a single block. Blocks are kept in their default state, the state gets picked on placement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemStack {
+    pub block: Arc<BlockInfo>,
+    pub count: u32,
+}
+
+/// Slots of items carried by the player, the first `HOTBAR_SIZE` of which make up the hotbar
+#[derive(Component, Clone, Debug)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+    selected_slot: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            slots: vec![None; INVENTORY_SIZE],
+            selected_slot: 0,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn slot(&self, slot: usize) -> Option<&ItemStack> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    pub fn selected_slot(&self) -> usize {
+        self.selected_slot
+    }
+
+    pub fn selected(&self) -> Option<&ItemStack> {
+        self.slot(self.selected_slot)
+    }
+
+    /// Selects a hotbar slot, ignoring slots outside of the hotbar
+    pub fn select(&mut self, slot: usize) {
+        if slot < HOTBAR_SIZE {
+            self.selected_slot = slot;
+        }
+    }
+
+    /// Moves the selection along the hotbar, wrapping around at either end
+    pub fn scroll(&mut self, slots: i32) {
+        self.selected_slot = (self.selected_slot as i32 + slots).rem_euclid(HOTBAR_SIZE as i32) as usize;
+    }
+
+    /// Adds `count` of `block`, topping up stacks of the same block first & filling empty slots after that.
+    /// Returns how many didn't fit.
+    pub fn add(&mut self, block: Arc<BlockInfo>, mut count: u32) -> u32 {
+        for stack in self.slots.iter_mut().flatten().filter(|stack| stack.block == block) {
+            let added = count.min(MAX_STACK_SIZE.saturating_sub(stack.count));
+            stack.count += added;
+            count -= added;
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if count == 0 {
+                break;
+            }
+
+            let added = count.min(MAX_STACK_SIZE);
+            *slot = Some(ItemStack { block: block.clone(), count: added });
+            count -= added;
+        }
+
+        count
+    }
+
+    /// Whether a single `block` would fit, either on top of a stack of it or in an empty slot
+    pub fn can_add(&self, block: &Arc<BlockInfo>) -> bool {
+        self.slots.iter().any(|slot| match slot {
+            Some(stack) => stack.block == *block && stack.count < MAX_STACK_SIZE,
+            None => true,
+        })
+    }
+
+    /// Takes a single block out of the selected slot, emptying the slot once its stack runs out
+    pub fn take_selected(&mut self) -> Option<Arc<BlockInfo>> {
+        let slot = &mut self.slots[self.selected_slot];
+        let stack = slot.as_mut()?;
+        let block = stack.block.clone();
+
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+
+        Some(block)
+    }
+}
+
+fn fill_starting_inventory(block_info_registry: Res<BlockInfoRegistry>, mut query: Query<&mut Inventory, With<Player>>) {
+    for mut inventory in query.iter_mut() {
+        for (registry_name, count) in STARTING_ITEMS {
+            match block_info_registry.try_get_block_info(registry_name) {
+                Ok(block_info) => {
+                    inventory.add(block_info, count);
+                }
+                Err(error) => warn!("Can't add starting item: {}", error),
+            }
+        }
+    }
+}
+
+/// Text along the bottom of the screen listing the hotbar, one section per slot
+#[derive(Component)]
+struct Hotbar;
+
+fn spawn_hotbar(mut commands: Commands) {
+    let sections = (0..HOTBAR_SIZE).map(|_| TextSection::new("", TextStyle { font_size: 18.0, ..default() }));
+
+    commands.spawn((
+        TextBundle::from_sections(sections).with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        }),
+        Hotbar,
+    ));
+}
+
+fn select_hotbar_slot(
+    key_input: Res<ButtonInput<KeyCode>>,
+    mut scroll_events: EventReader<MouseWheel>,
+    mut query: Query<(&mut Inventory, &CameraController), With<Player>>,
+) {
+    let Ok((mut inventory, camera_controller)) = query.get_single_mut() else {
+        return;
+    };
+
+    if let Some(slot) = HOTBAR_KEYS.iter().position(|key| key_input.just_pressed(*key)) {
+        inventory.select(slot);
+    }
+
+    let scroll = scroll_events
+        .read()
+        .map(|scroll_event| match scroll_event.unit {
+            MouseScrollUnit::Line => scroll_event.y,
+            MouseScrollUnit::Pixel => scroll_event.y / 16.0,
+        })
+        .sum::<f32>();
+
+    // Scrolling down moves on to the next slot, unless the scroll is changing the freecam's speed
+    if scroll.abs() >= 1.0 && !camera_controller.is_adjusting_speed(&key_input) {
+        inventory.scroll(-scroll.trunc() as i32);
+    }
+}
+
+fn update_hotbar(query_inventory: Query<&Inventory, (With<Player>, Changed<Inventory>)>, mut query_hotbar: Query<&mut Text, With<Hotbar>>) {
+    let (Ok(inventory), Ok(mut text)) = (query_inventory.get_single(), query_hotbar.get_single_mut()) else {
+        return;
+    };
+
+    for (slot, section) in text.sections.iter_mut().enumerate() {
+        let item = match inventory.slot(slot) {
+            Some(stack) => format!("{} x{}", stack.block.name, stack.count),
+            None => "-".to_string(),
+        };
+
+        section.value = format!("{}: {}   ", slot + 1, item);
+        section.style.color = match slot == inventory.selected_slot() {
+            true => Color::srgb(1.0, 0.85, 0.3),
+            false => Color::WHITE,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(name: &str) -> Arc<BlockInfo> {
+        Arc::new(BlockInfo {
+            category: Some("test".to_string()),
+            name: name.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn counts(inventory: &Inventory) -> Vec<(usize, String, u32)> {
+        (0..INVENTORY_SIZE)
+            .filter_map(|slot| inventory.slot(slot).map(|stack| (slot, stack.block.name.clone(), stack.count)))
+            .collect()
+    }
+
+    #[test]
+    fn stacks_merge_before_filling_empty_slots() {
+        let mut inventory = Inventory::default();
+
+        assert_eq!(inventory.add(block("dirt"), 10), 0);
+        assert_eq!(inventory.add(block("glass"), 1), 0);
+        assert_eq!(inventory.add(block("dirt"), 60), 0);
+
+        // Dirt tops up its stack to the limit, the rest goes to the first free slot after the glass
+        assert_eq!(counts(&inventory), vec![(0, "dirt".to_string(), 64), (1, "glass".to_string(), 1), (2, "dirt".to_string(), 6)]);
+
+        // Leftovers go to the earliest empty slot, even one emptied out before the existing stacks
+        inventory.select(1);
+        assert_eq!(inventory.take_selected(), Some(block("glass")));
+        assert_eq!(inventory.add(block("dirt"), 64), 0);
+        assert_eq!(counts(&inventory), vec![(0, "dirt".to_string(), 64), (1, "dirt".to_string(), 6), (2, "dirt".to_string(), 64)]);
+    }
+
+    #[test]
+    fn full_inventories_hand_back_what_doesnt_fit() {
+        let mut inventory = Inventory::default();
+
+        assert_eq!(inventory.add(block("dirt"), MAX_STACK_SIZE * INVENTORY_SIZE as u32 - 5), 0);
+        assert!(inventory.can_add(&block("dirt")));
+        assert!(!inventory.can_add(&block("glass")));
+        assert_eq!(inventory.add(block("glass"), 1), 1);
+        assert_eq!(inventory.add(block("dirt"), 8), 3);
+        assert!(!inventory.can_add(&block("dirt")));
+        assert!((0..INVENTORY_SIZE).all(|slot| inventory.slot(slot).is_some_and(|stack| stack.count == MAX_STACK_SIZE)));
+    }
+
+    #[test]
+    fn taking_from_the_selected_slot_uses_up_its_stack() {
+        let mut inventory = Inventory::default();
+        inventory.add(block("dirt"), 2);
+
+        assert_eq!(inventory.take_selected(), Some(block("dirt")));
+        assert_eq!(inventory.selected().map(|stack| stack.count), Some(1));
+        assert_eq!(inventory.take_selected(), Some(block("dirt")));
+        assert_eq!(inventory.selected(), None);
+        assert_eq!(inventory.take_selected(), None);
+    }
+
+    #[test]
+    fn selection_wraps_around_the_hotbar() {
+        let mut inventory = Inventory::default();
+
+        inventory.scroll(-1);
+        assert_eq!(inventory.selected_slot(), HOTBAR_SIZE - 1);
+        inventory.scroll(3);
+        assert_eq!(inventory.selected_slot(), 2);
+
+        // Slots past the hotbar can't be selected
+        inventory.select(HOTBAR_SIZE);
+        assert_eq!(inventory.selected_slot(), 2);
+    }
+}
@@ -0,0 +1,13 @@
+pub mod assets;
+pub mod atlas;
+pub mod setup;
+pub mod material;
+pub mod camera;
+pub mod character_controller;
+pub mod inventory;
+pub mod block_info;
+pub mod block_definition;
+pub mod block_state;
+pub mod block_model;
+pub mod world;
+pub mod player;
@@ -1,19 +1,13 @@
 use bevy::dev_tools::fps_overlay::{FpsOverlayConfig, FpsOverlayPlugin};
 use bevy::prelude::*;
 
-use crate::assets::GameAssetsPlugin;
-use crate::camera::CameraControllerPlugin;
-use crate::material::BlockAtlasMaterialPlugin;
-use crate::setup::SetupPlugin;
-use crate::world::WorldPlugin;
-
-mod assets;
-mod setup;
-mod material;
-mod camera;
-mod block_info;
-mod world;
-mod player;
+use client::assets::GameAssetsPlugin;
+use client::camera::CameraControllerPlugin;
+use client::character_controller::CharacterControllerPlugin;
+use client::inventory::InventoryPlugin;
+use client::material::BlockAtlasMaterialPlugin;
+use client::setup::SetupPlugin;
+use client::world::WorldPlugin;
 
 fn main() {
     App::new()
@@ -28,6 +22,8 @@ fn main() {
             },
         })
         .add_plugins(CameraControllerPlugin)
+        .add_plugins(CharacterControllerPlugin)
+        .add_plugins(InventoryPlugin)
         .add_plugins(GameAssetsPlugin)
         .add_plugins(BlockAtlasMaterialPlugin)
         .add_plugins(SetupPlugin)
@@ -8,32 +8,62 @@ use bevy::render::render_resource::{
 pub const ATTRIBUTE_ATLAS_TEXTURE_INDEX: MeshVertexAttribute =
     MeshVertexAttribute::new("AtlasTextureIndex", 6235423423, VertexFormat::Uint32);
 
+/// Ambient occlusion of a vertex, from 0 (open) to 3 (tucked into a corner)
+pub const ATTRIBUTE_AMBIENT_OCCLUSION: MeshVertexAttribute =
+    MeshVertexAttribute::new("AmbientOcclusion", 6235423424, VertexFormat::Float32);
+
+/// Sky light, then red, green & blue block light of a vertex, scaled to `0.0..=1.0`
+pub const ATTRIBUTE_VOXEL_LIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("VoxelLight", 6235423425, VertexFormat::Float32x4);
+
+/// Materials shared by every chunk, one for each of a chunk's meshes
 #[derive(Resource)]
-pub struct GlobalBlockAtlasMaterial(pub Handle<BlockAtlasMaterial>);
+pub struct GlobalBlockAtlasMaterial {
+    pub opaque: Handle<BlockAtlasMaterial>,
+    pub cutout: Handle<BlockAtlasMaterial>,
+    pub translucent: Handle<BlockAtlasMaterial>,
+}
 
+/// Block textures come from a 2D texture array, `ATTRIBUTE_ATLAS_TEXTURE_INDEX` picks the layer
 #[derive(Asset, AsBindGroup, Clone, Debug, TypePath)]
 pub struct BlockAtlasMaterial {
-    #[uniform(100)]
-    pub texture_size: Vec2,
-    #[texture(101)]
+    #[texture(101, dimension = "2d_array")]
     #[sampler(102)]
     pub atlas_texture: Handle<Image>,
+    pub alpha_mode: AlphaMode,
 }
 
 impl BlockAtlasMaterial {
-    pub fn new(atlas_texture: Handle<Image>, textures: &Assets<Image>) -> Self {
-        let texture_image = textures
-            .get(&atlas_texture)
-            .expect("block atlas texture not found");
-
+    pub fn new(atlas_texture: Handle<Image>) -> Self {
         Self {
-            texture_size: Vec2::new(texture_image.width() as f32, texture_image.height() as f32),
             atlas_texture,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+
+    /// Alpha-tested variant for the cut-out shapes of flowers & torches, whose texels are either fully opaque
+    /// or left out entirely
+    pub fn cutout(atlas_texture: Handle<Image>) -> Self {
+        Self {
+            alpha_mode: AlphaMode::Mask(0.5),
+            ..Self::new(atlas_texture)
+        }
+    }
+
+    /// Alpha-blended variant for translucent blocks, which get drawn after opaque ones, sorted back-to-front
+    pub fn translucent(atlas_texture: Handle<Image>) -> Self {
+        Self {
+            alpha_mode: AlphaMode::Blend,
+            ..Self::new(atlas_texture)
         }
     }
 }
 
 impl Material for BlockAtlasMaterial {
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
     fn fragment_shader() -> ShaderRef {
         "shaders/block-atlas.wgsl".into()
     }
@@ -52,6 +82,8 @@ impl Material for BlockAtlasMaterial {
             Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
             Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
             ATTRIBUTE_ATLAS_TEXTURE_INDEX.at_shader_location(3),
+            ATTRIBUTE_AMBIENT_OCCLUSION.at_shader_location(4),
+            ATTRIBUTE_VOXEL_LIGHT.at_shader_location(5),
         ])?;
         descriptor.vertex.buffers = vec![vertex_layout];
 
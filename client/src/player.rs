@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 use crate::camera::CameraController;
+use crate::character_controller::CharacterController;
+use crate::inventory::Inventory;
 
 #[derive(Component, Default)]
 pub struct Player;
@@ -8,6 +10,8 @@ pub struct Player;
 pub struct PlayerBundle {
     camera3d_bundle: Camera3dBundle,
     controller: CameraController,
+    character_controller: CharacterController,
+    inventory: Inventory,
     player_marker: Player,
 }
 
@@ -4,13 +4,14 @@ use bevy::render::texture::{
     ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor,
 };
 
-use crate::assets::{AppState, BlockTextureAtlasImage};
+use crate::assets::AppState;
+use crate::atlas::BlockTextureAtlas;
 use crate::material::{BlockAtlasMaterial, GlobalBlockAtlasMaterial};
 use crate::player::PlayerBundle;
 
 pub fn setup(
     mut commands: Commands,
-    atlas_image_handle: Res<BlockTextureAtlasImage>,
+    block_texture_atlas: Res<BlockTextureAtlas>,
     mut atlas_materials: ResMut<Assets<BlockAtlasMaterial>>,
     mut textures: ResMut<Assets<Image>>,
     mut wireframe_config: ResMut<WireframeConfig>,
@@ -18,7 +19,7 @@ pub fn setup(
     wireframe_config.global = true;
     wireframe_config.default_color = Color::srgb(0.2, 0.2, 0.2);
 
-    let texture = textures.get_mut(&atlas_image_handle.0).unwrap();
+    let texture = textures.get_mut(&block_texture_atlas.image).unwrap();
 
     texture.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
         address_mode_u: ImageAddressMode::Repeat,
@@ -26,16 +27,15 @@ pub fn setup(
         address_mode_w: ImageAddressMode::Repeat,
         mag_filter: ImageFilterMode::Nearest,
         min_filter: ImageFilterMode::Nearest,
-        mipmap_filter: ImageFilterMode::Nearest,
+        mipmap_filter: ImageFilterMode::Linear,
         ..Default::default()
     });
 
-    let atlas_material = atlas_materials.add(BlockAtlasMaterial::new(
-        atlas_image_handle.0.clone(),
-        &textures,
-    ));
-
-    commands.insert_resource(GlobalBlockAtlasMaterial(atlas_material));
+    commands.insert_resource(GlobalBlockAtlasMaterial {
+        opaque: atlas_materials.add(BlockAtlasMaterial::new(block_texture_atlas.image.clone())),
+        cutout: atlas_materials.add(BlockAtlasMaterial::cutout(block_texture_atlas.image.clone())),
+        translucent: atlas_materials.add(BlockAtlasMaterial::translucent(block_texture_atlas.image.clone())),
+    });
 
     commands.insert_resource(AmbientLight {
         color: Color::WHITE,
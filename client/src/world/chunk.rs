@@ -1,31 +1,89 @@
-use std::sync::Arc;
+use std::collections::BTreeSet;
 
 use bevy::prelude::*;
 use bevy::render::mesh::{Indices, PrimitiveTopology};
 use bevy::render::render_asset::RenderAssetUsages;
-use block_mesh::{greedy_quads, GreedyQuadsBuffer, RIGHT_HANDED_Y_UP_CONFIG};
+use bevy::tasks::Task;
+use block_mesh::{greedy_quads, GreedyQuadsBuffer, OrientedBlockFace, UnorientedQuad, RIGHT_HANDED_Y_UP_CONFIG};
 use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+use strum::IntoEnumIterator;
 
-use crate::block_info::{BlockInfoRegistry, BlockSide};
-use crate::material::{ATTRIBUTE_ATLAS_TEXTURE_INDEX, BlockAtlasPbrBundle, GlobalBlockAtlasMaterial};
-use crate::world::block::Block;
-use crate::world::voxel::BlockVoxel;
+use crate::block_info::{BlockId, BlockInfo, BlockInfoRegistry, BlockSide, AIR_BLOCK_ID, MISSING_BLOCK_NAME};
+use crate::block_model::BlockModelQuad;
+use crate::material::{ATTRIBUTE_AMBIENT_OCCLUSION, ATTRIBUTE_ATLAS_TEXTURE_INDEX, ATTRIBUTE_VOXEL_LIGHT};
+use crate::world::light::{compute_face_light, ChunkLightData, ChunkLightVolume, VoxelLight};
+use crate::world::occlusion::{compute_face_occlusion, vertex_occlusion};
+use crate::world::palette::PalettedContainer;
+use crate::world::voxel::{BlockVoxel, FaceVoxel, VoxelFace};
 
 pub const CHUNK_SIZE: u32 = 16;
 pub const CHUNK_SIZE_OUTER: u32 = CHUNK_SIZE + 2;
 
+pub type ChunkBlockShape = ConstShape3u32<CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE>;
 pub type ChunkVoxelShape = ConstShape3u32<CHUNK_SIZE_OUTER, CHUNK_SIZE_OUTER, CHUNK_SIZE_OUTER>;
 
+/// Splits a world-space block position into the position of the chunk containing it
+/// and the chunk-local position of the block
+pub fn split_block_position(block_position: IVec3) -> (IVec3, UVec3) {
+    let chunk_size = IVec3::splat(CHUNK_SIZE as i32);
+
+    (block_position.div_euclid(chunk_size), block_position.rem_euclid(chunk_size).as_uvec3())
+}
+
+/// Runtime IDs of a chunk's blocks, `AIR_BLOCK_ID` being air. Stored paletted, as chunks tend to consist of
+/// only a handful of distinct blocks.
 #[derive(Clone, Component, Debug)]
-pub struct ChunkBlockData(pub [Option<Arc<Block>>; CHUNK_SIZE as usize * CHUNK_SIZE as usize * CHUNK_SIZE as usize]);
+pub struct ChunkBlockData(PalettedContainer<BlockId>);
 
 impl Default for ChunkBlockData {
     fn default() -> Self {
-        Self(core::array::from_fn(|_i| None))
+        Self::filled(AIR_BLOCK_ID)
     }
 }
 
-#[derive(Clone, Component, Debug)]
+impl ChunkBlockData {
+    /// Chunk made up of a single block throughout
+    pub fn filled(block_id: BlockId) -> Self {
+        Self(PalettedContainer::new(ChunkBlockShape::USIZE, block_id))
+    }
+
+    /// ID of the block at a chunk-local position, each coordinate within `0..CHUNK_SIZE`
+    pub fn get(&self, position: UVec3) -> BlockId {
+        *self.0.get(ChunkBlockShape::linearize(position.to_array()) as usize)
+    }
+
+    pub fn set(&mut self, position: UVec3, block_id: BlockId) {
+        self.0.set(ChunkBlockShape::linearize(position.to_array()) as usize, block_id);
+    }
+
+    /// Drops blocks that are no longer part of the chunk from its palette, see `PalettedContainer::compact`
+    pub fn compact(&mut self) {
+        self.0.compact();
+    }
+
+    /// Palette & packed indices of the chunk's storage, see `PalettedContainer::raw_parts`
+    pub fn raw_parts(&self) -> (&[BlockId], u32, &[u64]) {
+        self.0.raw_parts()
+    }
+
+    /// Block data made of the `raw_parts` of another chunk, `None` if they don't make up a chunk
+    pub fn from_raw_parts(palette: Vec<BlockId>, bits: u32, words: Vec<u64>) -> Option<Self> {
+        PalettedContainer::from_raw_parts(ChunkBlockShape::USIZE, palette, bits, words).map(Self)
+    }
+
+    /// ID of every block of the chunk, in `ChunkBlockShape` order
+    pub fn iter(&self) -> impl Iterator<Item = BlockId> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Bytes taken by the chunk's storage
+    pub fn memory_usage(&self) -> usize {
+        self.0.memory_usage()
+    }
+}
+
+/// Voxels a chunk mesh gets built from, only kept around while meshing
+#[derive(Clone, Debug)]
 pub struct ChunkVoxelData(pub [BlockVoxel; CHUNK_SIZE_OUTER as usize * CHUNK_SIZE_OUTER as usize * CHUNK_SIZE_OUTER as usize]);
 
 impl Default for ChunkVoxelData {
@@ -34,15 +92,112 @@ impl Default for ChunkVoxelData {
     }
 }
 
+/// Block data of the six chunks sharing a face with a chunk, indexed by `BlockSide`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChunkNeighbours<'a>(pub [Option<&'a ChunkBlockData>; 6]);
+
+impl<'a> ChunkNeighbours<'a> {
+    pub fn from_lookup(chunk_position: IVec3, mut lookup: impl FnMut(IVec3) -> Option<&'a ChunkBlockData>) -> Self {
+        let mut neighbours = Self::default();
+
+        for side in BlockSide::iter() {
+            neighbours.0[side as usize] = lookup(chunk_position + side.normal());
+        }
+
+        neighbours
+    }
+}
+
+impl ChunkVoxelData {
+    /// Voxels derived from block data alone, the one-voxel border around the chunk is left as air
+    pub fn from_block_data(block_data: &ChunkBlockData, block_info_registry: &BlockInfoRegistry) -> Self {
+        let mut voxels = Self::default();
+
+        for (i, block_id) in block_data.iter().enumerate() {
+            let [x, y, z] = ChunkBlockShape::delinearize(i as u32);
+
+            voxels.0[ChunkVoxelShape::linearize([x + 1, y + 1, z + 1]) as usize] = BlockVoxel::from_block_id(block_id, block_info_registry);
+        }
+
+        voxels
+    }
+
+    /// Same as `from_block_data`, but the one-voxel border gets filled with the touching layer
+    /// of each loaded neighbour, so faces hidden behind a neighbouring chunk don't get meshed.
+    pub fn from_block_data_with_neighbours(block_data: &ChunkBlockData, neighbours: &ChunkNeighbours, block_info_registry: &BlockInfoRegistry) -> Self {
+        const FAR: u32 = CHUNK_SIZE_OUTER - 1;
+        const LAST: u32 = CHUNK_SIZE - 1;
+
+        let mut voxels = Self::from_block_data(block_data, block_info_registry);
+
+        for side in BlockSide::iter() {
+            let Some(neighbour) = neighbours.0[side as usize] else {
+                continue;
+            };
+
+            for a in 0..CHUNK_SIZE {
+                for b in 0..CHUNK_SIZE {
+                    let (padding_position, neighbour_position) = match side {
+                        BlockSide::Front => ([a + 1, b + 1, FAR], [a, b, 0]),
+                        BlockSide::Back => ([a + 1, b + 1, 0], [a, b, LAST]),
+                        BlockSide::Left => ([0, a + 1, b + 1], [LAST, a, b]),
+                        BlockSide::Right => ([FAR, a + 1, b + 1], [0, a, b]),
+                        BlockSide::Top => ([a + 1, FAR, b + 1], [a, 0, b]),
+                        BlockSide::Bottom => ([a + 1, 0, b + 1], [a, LAST, b]),
+                    };
+                    let block_id = neighbour.get(UVec3::from_array(neighbour_position));
+
+                    voxels.0[ChunkVoxelShape::linearize(padding_position) as usize] = BlockVoxel::from_block_id(block_id, block_info_registry);
+                }
+            }
+        }
+
+        voxels
+    }
+}
+
 #[derive(Component, Clone, Debug, Default, Eq, PartialEq)]
 pub struct ChunkPosition(pub IVec3);
 
+impl ChunkPosition {
+    /// World-space position of the chunk's local origin, i.e. its block with the lowest coordinates
+    pub fn world_origin(&self) -> Vec3 {
+        (self.0 * CHUNK_SIZE as i32).as_vec3()
+    }
+}
+
+/// Marks chunks whose block data got edited since they were loaded, so they have to be saved on unload
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ChunkModified;
+
+/// Marks chunks whose voxels & mesh have to be rebuilt, either because their own block data
+/// changed or because one of their neighbours did.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ChunkMeshDirty;
+
+/// Block data of a chunk being loaded from disk or generated on the async compute task pool
+#[derive(Component)]
+pub struct ChunkGenerationTask(pub Task<ChunkBlockData>);
+
+/// Meshes of a chunk being built on the async compute task pool
+#[derive(Component)]
+pub struct ChunkMeshTask(pub Task<ChunkMeshes>);
+
+/// Translucent mesh of a chunk, rendered by a child entity placed at the chunk's center.
+/// Chunks without translucent blocks have no such child until they get some.
+#[derive(Component, Clone, Debug)]
+pub struct ChunkTranslucentMesh(pub Handle<Mesh>);
+
+/// Cutout mesh of a chunk, rendered alpha-tested by a child entity at the chunk's origin.
+/// Chunks without flowers, torches & the like have no such child until they get some.
+#[derive(Component, Clone, Debug)]
+pub struct ChunkCutoutMesh(pub Handle<Mesh>);
 
 #[derive(Bundle, Clone, Debug, Default)]
 pub struct Chunk {
     block_data: ChunkBlockData,
+    light_data: ChunkLightData,
     position: ChunkPosition,
-    voxels: ChunkVoxelData,
 }
 
 impl Chunk {
@@ -53,138 +208,444 @@ impl Chunk {
         }
     }
 
-    pub fn spawn(mut self, commands: &mut Commands, meshes: &mut ResMut<Assets<Mesh>>, atlas_material: &GlobalBlockAtlasMaterial, block_info_registry: &BlockInfoRegistry) {
-        self.update_voxels(block_info_registry);
+    pub fn from_block_data(position: IVec3, block_data: ChunkBlockData) -> Self {
+        Self {
+            block_data,
+            ..Self::new(position)
+        }
+    }
+}
+
+/// Meshes a chunk gets rendered with. Translucent quads are kept apart so they can be drawn alpha-blended,
+/// after everything opaque, and so are models with see-through texels, which need alpha testing.
+pub struct ChunkMeshes {
+    /// Mesh in chunk-local space: block at local position `p` spans `p..p + 1`
+    pub opaque: Mesh,
+    /// Mesh of non-cube models that aren't translucent, in chunk-local space like `opaque`
+    pub cutout: Mesh,
+    /// Mesh relative to `TRANSLUCENT_MESH_OFFSET`, i.e. the chunk's center. Transparent meshes get sorted by
+    /// the distance to their entity's origin, so that origin has to be the chunk's center to draw nearer
+    /// chunks over farther ones.
+    pub translucent: Mesh,
+}
 
-        let chunk_mesh = self.serialize_voxels_to_render_mesh(block_info_registry);
+pub const TRANSLUCENT_MESH_OFFSET: Vec3 = Vec3::splat(CHUNK_SIZE as f32 / 2.0);
+
+/// Vertex attributes of a mesh being built, quad by quad
+#[derive(Default)]
+struct ChunkMeshBuilder {
+    indices: Vec<u32>,
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    atlas_texture_indices: Vec<u32>,
+    ambient_occlusion: Vec<f32>,
+    voxel_light: Vec<[f32; 4]>,
+}
 
-        commands.spawn((self, BlockAtlasPbrBundle {
-            mesh: meshes.add(chunk_mesh),
-            material: atlas_material.0.clone(),
-            ..Default::default()
-        }));
+impl ChunkMeshBuilder {
+    /// `occlusion` is the ambient occlusion of each of the quad's corners, in `quad_mesh_positions` order
+    fn push_greedy_quad(&mut self, face: &OrientedBlockFace, quad: &UnorientedQuad, offset: Vec3, block_texture_id: u32, occlusion: [u8; 4], light: VoxelLight) {
+        let mut quad_positions = face.quad_mesh_positions(quad, 1.0);
+
+        for vertex in quad_positions.iter_mut() {
+            *vertex = (Vec3::from(*vertex) - offset).to_array();
+        }
+
+        let start = self.positions.len() as u32;
+        let mut quad_indices = face.quad_mesh_indices(start);
+
+        // Quads get split along the diagonal between their 2nd & 3rd corner. Splitting along the more occluded
+        // diagonal instead keeps the occlusion gradient symmetric, rather than depending on the quad's orientation.
+        if occlusion[0] + occlusion[3] > occlusion[1] + occlusion[2] {
+            let [first, second, third, ..] = quad_indices;
+            quad_indices = [first, second, start + 3, first, start + 3, third];
+        }
+
+        self.indices.extend_from_slice(&quad_indices);
+        self.positions.extend_from_slice(&quad_positions);
+        self.normals.extend_from_slice(&face.quad_mesh_normals());
+        self.tex_coords.extend_from_slice(&face.tex_coords(RIGHT_HANDED_Y_UP_CONFIG.u_flip_face, true, quad));
+        self.atlas_texture_indices.extend_from_slice(&[block_texture_id; 4]);
+        self.ambient_occlusion.extend(occlusion.map(f32::from));
+        self.voxel_light.extend_from_slice(&[light.to_vertex_light(); 4]);
     }
 
-    pub fn get_block(&self, position: Vec3) -> Option<Arc<Block>> {
-        let block_position = position.floor().as_ivec3();
-        let block_index = block_position.x + block_position.y * CHUNK_SIZE as i32 + block_position.z * CHUNK_SIZE as i32 * CHUNK_SIZE as i32;
+    /// Quad of a non-cube model of the block at `block_position`, rotated the way the block is & lit by the block's own voxel
+    fn push_model_quad(&mut self, quad: &BlockModelQuad, block_info: &BlockInfo, block_position: Vec3, light: VoxelLight) {
+        let block_center = block_position + Vec3::splat(0.5);
+        let positions = quad.positions.map(|position| (block_center + block_info.rotation.rotate(position - Vec3::splat(0.5))).to_array());
+        let normal = block_info.rotation.rotate(quad.normal).to_array();
+        let block_texture_id = block_info.side_texture_ids[quad.side as usize].unwrap_or(255);
+
+        let start = self.positions.len() as u32;
+        self.indices.extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+        self.positions.extend_from_slice(&positions);
+        self.normals.extend_from_slice(&[normal; 4]);
+        self.tex_coords.extend_from_slice(&quad.tex_coords.map(|tex_coord| tex_coord.to_array()));
+        self.atlas_texture_indices.extend_from_slice(&[block_texture_id; 4]);
+        self.ambient_occlusion.extend_from_slice(&[0.0; 4]);
+        self.voxel_light.extend_from_slice(&[light.to_vertex_light(); 4]);
+    }
 
-        self.block_data.0[block_index as usize].clone()
+    fn build(self) -> Mesh {
+        let mut render_mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+
+        render_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        render_mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        render_mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.tex_coords);
+        render_mesh.insert_attribute(ATTRIBUTE_ATLAS_TEXTURE_INDEX, self.atlas_texture_indices);
+        render_mesh.insert_attribute(ATTRIBUTE_AMBIENT_OCCLUSION, self.ambient_occlusion);
+        render_mesh.insert_attribute(ATTRIBUTE_VOXEL_LIGHT, self.voxel_light);
+        render_mesh.insert_indices(Indices::U32(self.indices));
+
+        render_mesh
     }
+}
 
-    // TODO: update voxels from blocks
-    pub fn update_voxels(&mut self, block_info_registry: &BlockInfoRegistry) {
-        let mut voxels = [BlockVoxel::AIR; CHUNK_SIZE_OUTER as usize * CHUNK_SIZE_OUTER as usize * CHUNK_SIZE_OUTER as usize];
+impl ChunkVoxelData {
+    /// Meshes of the chunk lit by `light`, see `ChunkMeshes` for the space each one is in
+    pub fn to_render_meshes(&self, light: &ChunkLightVolume, block_info_registry: &BlockInfoRegistry) -> ChunkMeshes {
+        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+        let voxels = &self.0;
+
+        let mut voxel_faces = std::array::from_fn(|_| vec![VoxelFace::default(); voxels.len()]);
+        compute_face_occlusion(voxels, &mut voxel_faces);
+        compute_face_light(voxels, &mut voxel_faces, light);
+
+        let mut opaque = ChunkMeshBuilder::default();
+        let mut cutout = ChunkMeshBuilder::default();
+        let mut translucent = ChunkMeshBuilder::default();
+
+        // IDs that don't resolve get reported once per mesh, rather than for every quad they show up in
+        let mut unknown_block_ids = BTreeSet::new();
+        let mut get_block_info = |block_id| {
+            block_info_registry.try_get_block_info_by_id(block_id).cloned().unwrap_or_else(|_| {
+                unknown_block_ids.insert(block_id);
+                block_info_registry.missing_block_info()
+            })
+        };
+
+        // Every side gets a `greedy_quads` pass of its own, so its faces merge by that side's occlusion & light alone
+        let mut buffer = GreedyQuadsBuffer::new(voxels.len());
+        let mut face_voxels = Vec::with_capacity(voxels.len());
+
+        for (i, face) in faces.iter().enumerate() {
+            let normals: Vec3 = face.quad_mesh_normals()[0].into();
+            let block_side = BlockSide::match_normal_vector(normals);
+
+            face_voxels.clear();
+            face_voxels.extend(
+                voxels
+                    .iter()
+                    .zip(voxel_faces[block_side as usize].iter())
+                    .map(|(block, face)| FaceVoxel { block: *block, face: *face }),
+            );
+            greedy_quads(
+                &face_voxels,
+                &ChunkVoxelShape {},
+                [0; 3],
+                [CHUNK_SIZE_OUTER - 1; 3],
+                &faces,
+                &mut buffer,
+            );
+
+            for quad in std::mem::take(&mut buffer.quads.groups[i]) {
+                let block_info = get_block_info(quad.voxel.block.block_id);
+                let block_texture_id = block_info.get_side_texture_id(block_side).unwrap_or(255);
 
-        for z in 1..CHUNK_SIZE_OUTER - 1 {
-            for y in 1..CHUNK_SIZE_OUTER - 1 {
-                for x in 1..CHUNK_SIZE_OUTER - 1 {
-                    let i = ChunkVoxelShape::linearize([x, y, z]);
-                    let block_info = match y {
-                        _ if y == (CHUNK_SIZE_OUTER - 2) => block_info_registry.get_block_info("potato_crust:grass"),
-                        _ if y < (CHUNK_SIZE_OUTER - 2) && y > (CHUNK_SIZE_OUTER - 5) => block_info_registry.get_block_info("potato_crust:dirt"),
-                        _ => block_info_registry.get_block_info("potato_crust:cobblestone"),
-                    };
+                let quad_positions = face.quad_mesh_positions(&quad.into(), 1.0).map(Vec3::from);
+                let quad_center = quad_positions.iter().sum::<Vec3>() / 4.0;
+                let occlusion = quad_positions.map(|vertex| vertex_occlusion(voxels, vertex, normals, quad_center));
+                let face_light = VoxelLight::from_bits(quad.voxel.face.light);
 
-                    voxels[i as usize] = BlockVoxel {
-                        block_info_key_hash: block_info.get_registry_name_hash(),
-                        is_air: false,
-                        is_translucent: false,
-                    };
+                // Voxels are offset by the one-voxel padding around the chunk
+                match quad.voxel.block.is_translucent {
+                    false => opaque.push_greedy_quad(face, &quad.into(), Vec3::ONE, block_texture_id, occlusion, face_light),
+                    true => translucent.push_greedy_quad(face, &quad.into(), Vec3::ONE + TRANSLUCENT_MESH_OFFSET, block_texture_id, occlusion, face_light),
                 }
             }
         }
 
-        self.voxels.0 = voxels;
-    }
+        // Other models go block by block, without culling any of their faces
+        for i in 0..ChunkBlockShape::SIZE {
+            let block_position = ChunkBlockShape::delinearize(i);
+            let padded_position = block_position.map(|c| c + 1);
+            let voxel = voxels[ChunkVoxelShape::linearize(padded_position) as usize];
 
-    pub fn serialize_voxels_to_render_mesh(&self, block_info_registry: &BlockInfoRegistry) -> Mesh {
-        let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
+            if voxel.is_air() || voxel.is_cube {
+                continue;
+            }
 
-        let position_offset = self.position.0 * CHUNK_SIZE as i32;
-
-        let mut buffer = GreedyQuadsBuffer::new(self.voxels.0.len());
-        // let mut buffer = UnitQuadBuffer::new();
-        greedy_quads(
-            &self.voxels.0,
-            &ChunkVoxelShape {},
-            [0; 3],
-            [CHUNK_SIZE_OUTER - 1; 3],
-            &faces,
-            &mut buffer,
-        );
-        let num_indices = buffer.quads.num_quads() * 6;
-        let num_vertices = buffer.quads.num_quads() * 4;
-
-        // visible_block_faces(
-        //     &voxels,
-        //     &SampleShape {},
-        //     [0; 3],
-        //     [CHUNK_SIZE_OUTER - 1; 3],
-        //     &faces,
-        //     &mut buffer,
-        // );
-        // let num_indices = buffer.num_quads() * 6;
-        // let num_vertices = buffer.num_quads() * 4;
-
-        let mut indices: Vec<u32> = Vec::with_capacity(num_indices);
-        let mut positions: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
-        let mut normals = Vec::with_capacity(num_vertices);
-        let mut tex_coords = Vec::with_capacity(num_vertices);
-        let mut atlas_texture_indices = Vec::with_capacity(num_vertices);
-
-        // info!("Total number of quads: {}", buffer.num_quads());
-        // info!("Total number of quads: {}", buffer.quads.num_quads());
-
-        // for (group, face) in buffer.groups.into_iter().zip(faces.into_iter()) {
-        for (group, face) in buffer.quads.groups.into_iter().zip(faces.into_iter()) {
-            for quad in group.into_iter() {
-                let voxel_position = &mut face.quad_mesh_positions(&quad.into(), 1.0);
-
-                for vertex in voxel_position.iter_mut() {
-                    vertex[0] += position_offset.x as f32;
-                    vertex[1] += position_offset.y as f32;
-                    vertex[2] += position_offset.z as f32;
+            let block_info = get_block_info(voxel.block_id);
+            let block_position = UVec3::from_array(block_position).as_vec3();
+            let block_light = light.get(UVec3::from_array(padded_position));
+
+            for model_quad in block_info.model.quads() {
+                match voxel.is_translucent {
+                    false => cutout.push_model_quad(&model_quad, &block_info, block_position, block_light),
+                    true => translucent.push_model_quad(&model_quad, &block_info, block_position - TRANSLUCENT_MESH_OFFSET, block_light),
                 }
+            }
+        }
 
-                indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
-                positions.extend_from_slice(voxel_position.as_slice());
-                normals.extend_from_slice(&face.quad_mesh_normals());
-                tex_coords.extend_from_slice(&face.tex_coords(
-                    RIGHT_HANDED_Y_UP_CONFIG.u_flip_face,
-                    true,
-                    &quad.into(),
-                ));
-
-                let normals: Vec3 = face.quad_mesh_normals()[0].into();
-                let block_side = BlockSide::match_normal_vector(normals);
-                let block_info = block_info_registry.get_block_info_by_hash(quad.voxel.block_info_key_hash);
-                let block_texture_id = block_info.get_side_texture_id(block_side).unwrap_or(255);
+        if !unknown_block_ids.is_empty() {
+            warn!("Meshing chunk with unknown block IDs {:?}, substituting `{}`", unknown_block_ids, MISSING_BLOCK_NAME);
+        }
 
-                atlas_texture_indices.extend_from_slice(&[
-                    block_texture_id,
-                    block_texture_id,
-                    block_texture_id,
-                    block_texture_id,
-                ]);
-            }
+        ChunkMeshes {
+            opaque: opaque.build(),
+            cutout: cutout.build(),
+            translucent: translucent.build(),
         }
-        // Center the mesh.
-        for p in &mut positions {
-            *p = (Vec3::from(*p) - Vec3::splat(10.0)).into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::mesh::VertexAttributeValues;
+
+    use super::*;
+    use crate::world::generator::{HeightmapWorldGenerator, WorldGenerator, DEFAULT_WORLD_SEED};
+
+    const CHUNK_SIZE_OUTER_LAST: u32 = CHUNK_SIZE_OUTER - 1;
+
+    #[test]
+    fn voxels_are_reproducible_from_block_data() {
+        let registry = BlockInfoRegistry::for_tests();
+        let position = IVec3::new(2, 0, -1);
+        let chunk = Chunk::from_block_data(position, HeightmapWorldGenerator::default().generate_chunk(DEFAULT_WORLD_SEED, &ChunkPosition(position), &registry));
+        let voxels = ChunkVoxelData::from_block_data(&chunk.block_data, &registry);
+
+        assert_eq!(voxels.0, ChunkVoxelData::from_block_data(&chunk.block_data, &registry).0);
+
+        for i in 0..ChunkVoxelShape::SIZE {
+            let [x, y, z] = ChunkVoxelShape::delinearize(i);
+            let voxel = voxels.0[i as usize];
+            let is_border = [x, y, z].iter().any(|&c| c == 0 || c == CHUNK_SIZE_OUTER - 1);
+
+            let expected = match is_border {
+                true => BlockVoxel::AIR,
+                false => BlockVoxel::from_block_id(chunk.block_data.get(UVec3::new(x - 1, y - 1, z - 1)), &registry),
+            };
+
+            assert_eq!(voxel, expected, "voxel at ({x}, {y}, {z}) does not match block data");
         }
+    }
 
-        let mut render_mesh = Mesh::new(
-            PrimitiveTopology::TriangleList,
-            RenderAssetUsages::RENDER_WORLD,
+    #[test]
+    fn block_edits_show_up_in_voxels_and_mesh() {
+        let registry = BlockInfoRegistry::for_tests();
+        let dirt = registry.get_block_info("potato_crust:dirt");
+        let mut block_data = ChunkBlockData::default();
+
+        assert_eq!(ChunkVoxelData::from_block_data(&block_data, &registry).to_render_meshes(&ChunkLightVolume::default(), &registry).opaque.count_vertices(), 0);
+
+        block_data.set(UVec3::new(3, 4, 5), dirt.id);
+        let voxels = ChunkVoxelData::from_block_data(&block_data, &registry);
+
+        assert_eq!(voxels.0[ChunkVoxelShape::linearize([4, 5, 6]) as usize].block_id, dirt.id);
+        let mesh = voxels.to_render_meshes(&ChunkLightVolume::default(), &registry).opaque;
+        assert_eq!(mesh.count_vertices(), 6 * 4);
+
+        // Rendered cube covers exactly the block's chunk-local cell
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            panic!("mesh has no vertex positions");
+        };
+        let min = positions.iter().fold(Vec3::MAX, |min, position| min.min(Vec3::from(*position)));
+        let max = positions.iter().fold(Vec3::MIN, |max, position| max.max(Vec3::from(*position)));
+        assert_eq!((min, max), (Vec3::new(3.0, 4.0, 5.0), Vec3::new(4.0, 5.0, 6.0)));
+
+        block_data.set(UVec3::new(3, 4, 5), AIR_BLOCK_ID);
+
+        assert_eq!(ChunkVoxelData::from_block_data(&block_data, &registry).0, ChunkVoxelData::default().0);
+    }
+
+    #[test]
+    fn border_is_padded_from_neighbours() {
+        let registry = BlockInfoRegistry::for_tests();
+        let dirt = registry.get_block_info("potato_crust:dirt").id;
+
+        let mut block_data = ChunkBlockData::default();
+        block_data.set(UVec3::new(CHUNK_SIZE - 1, 0, 0), dirt);
+
+        let mut right_neighbour = ChunkBlockData::default();
+        right_neighbour.set(UVec3::new(0, 0, 0), dirt);
+        right_neighbour.set(UVec3::new(0, 7, 9), dirt);
+        right_neighbour.set(UVec3::new(1, 0, 0), dirt);
+
+        let neighbours = ChunkNeighbours::from_lookup(IVec3::ZERO, |position| (position == IVec3::X).then_some(&right_neighbour));
+        let voxels = ChunkVoxelData::from_block_data_with_neighbours(&block_data, &neighbours, &registry);
+
+        for i in 0..ChunkVoxelShape::SIZE {
+            let [x, y, z] = ChunkVoxelShape::delinearize(i);
+            let is_solid = !voxels.0[i as usize].is_air();
+            let expected_solid = matches!([x, y, z], [CHUNK_SIZE, 1, 1] | [CHUNK_SIZE_OUTER_LAST, 1, 1] | [CHUNK_SIZE_OUTER_LAST, 8, 10]);
+
+            assert_eq!(is_solid, expected_solid, "unexpected voxel at ({x}, {y}, {z})");
+        }
+
+        // The face touching the neighbour's block is hidden, the other five remain
+        let mesh = voxels.to_render_meshes(&ChunkLightVolume::default(), &registry).opaque;
+        assert_eq!(mesh.count_vertices(), 5 * 4);
+    }
+
+    fn bounds(mesh: &Mesh) -> (Vec3, Vec3) {
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            panic!("mesh has no vertex positions");
+        };
+
+        positions.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), position| {
+            (min.min(Vec3::from(*position)), max.max(Vec3::from(*position)))
+        })
+    }
+
+    // Unlike vertex counts, doesn't depend on how many faces got merged into a single quad
+    fn surface_area(mesh: &Mesh) -> f32 {
+        let (Some(VertexAttributeValues::Float32x3(positions)), Some(Indices::U32(indices))) = (mesh.attribute(Mesh::ATTRIBUTE_POSITION), mesh.indices()) else {
+            panic!("mesh has no vertex positions or indices");
+        };
+
+        indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(positions[triangle[i] as usize]));
+                (b - a).cross(c - a).length() / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn translucent_blocks_get_a_mesh_of_their_own() {
+        let registry = BlockInfoRegistry::for_tests();
+        let glass = registry.get_block_info("potato_crust:glass");
+        let mut block_data = ChunkBlockData::default();
+
+        block_data.set(UVec3::new(3, 4, 5), registry.get_block_info("potato_crust:dirt").id);
+        block_data.set(UVec3::new(4, 4, 5), glass.id);
+        block_data.set(UVec3::new(5, 4, 5), glass.id);
+
+        let meshes = ChunkVoxelData::from_block_data(&block_data, &registry).to_render_meshes(&ChunkLightVolume::default(), &registry);
+
+        // Dirt stays visible through the glass, while glass hides the faces between glass blocks
+        // and the face it shares with the dirt
+        assert_eq!(surface_area(&meshes.opaque), 6.0);
+        assert_eq!(surface_area(&meshes.translucent), 10.0 - 1.0);
+
+        assert_eq!(bounds(&meshes.opaque), (Vec3::new(3.0, 4.0, 5.0), Vec3::new(4.0, 5.0, 6.0)));
+        assert_eq!(
+            bounds(&meshes.translucent),
+            (Vec3::new(4.0, 4.0, 5.0) - TRANSLUCENT_MESH_OFFSET, Vec3::new(6.0, 5.0, 6.0) - TRANSLUCENT_MESH_OFFSET)
         );
+    }
 
-        render_mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
-        render_mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-        render_mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, tex_coords);
-        render_mesh.insert_attribute(ATTRIBUTE_ATLAS_TEXTURE_INDEX, atlas_texture_indices);
-        render_mesh.insert_indices(Indices::U32(indices));
+    #[test]
+    fn models_are_meshed_without_culling_their_neighbours() {
+        let registry = BlockInfoRegistry::for_tests();
+        let mut block_data = ChunkBlockData::default();
 
-        render_mesh
+        block_data.set(UVec3::new(3, 4, 5), registry.get_block_info("potato_crust:dirt").id);
+        block_data.set(UVec3::new(3, 5, 5), registry.get_block_info("potato_crust:poppy").id);
+        block_data.set(UVec3::new(4, 4, 5), registry.get_block_info("potato_crust:torch").id);
+
+        let meshes = ChunkVoxelData::from_block_data(&block_data, &registry).to_render_meshes(&ChunkLightVolume::default(), &registry);
+
+        // Full dirt cube stays opaque, both sides of the poppy's two diagonal planes & the torch's box get cut out
+        let torch_area = 4.0 * (2.0 * 10.0) / 256.0 + 2.0 * (2.0 * 2.0) / 256.0;
+        let expected_area = 4.0 * std::f32::consts::SQRT_2 + torch_area;
+        assert_eq!(surface_area(&meshes.opaque), 6.0);
+        assert!((surface_area(&meshes.cutout) - expected_area).abs() < 1e-4, "{}", surface_area(&meshes.cutout));
+
+        assert_eq!(bounds(&meshes.opaque), (Vec3::new(3.0, 4.0, 5.0), Vec3::new(4.0, 5.0, 6.0)));
+        assert_eq!(bounds(&meshes.cutout), (Vec3::new(3.0, 4.0, 5.0), Vec3::new(4.5625, 6.0, 6.0)));
+        assert_eq!(meshes.translucent.count_vertices(), 0);
+    }
+
+    #[test]
+    fn faces_merge_by_the_occlusion_of_their_own_side() {
+        let registry = BlockInfoRegistry::for_tests();
+        let dirt = registry.get_block_info("potato_crust:dirt").id;
+        let mut block_data = ChunkBlockData::default();
+
+        // Row of blocks with one block below & in front of the second, shading only the front faces around it
+        for x in 3..9 {
+            block_data.set(UVec3::new(x, 4, 5), dirt);
+        }
+        block_data.set(UVec3::new(4, 3, 6), dirt);
+
+        let mesh = ChunkVoxelData::from_block_data(&block_data, &registry).to_render_meshes(&ChunkLightVolume::default(), &registry).opaque;
+        let (Some(VertexAttributeValues::Float32x3(positions)), Some(VertexAttributeValues::Float32x3(normals))) =
+            (mesh.attribute(Mesh::ATTRIBUTE_POSITION), mesh.attribute(Mesh::ATTRIBUTE_NORMAL))
+        else {
+            panic!("mesh has no vertex positions or normals");
+        };
+        let count_vertices = |normal: [f32; 3], is_on_plane: &dyn Fn([f32; 3]) -> bool| {
+            (0..positions.len()).filter(|i| normals[*i] == normal && is_on_plane(positions[*i])).count()
+        };
+
+        // The row's top stays a single quad, its front gets split into the three shaded faces & the rest
+        assert_eq!(count_vertices([0.0, 1.0, 0.0], &|position| position[1] == 5.0), 4);
+        assert_eq!(count_vertices([0.0, 0.0, 1.0], &|position| position[2] == 6.0 && position[1] >= 4.0), 4 * 4);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn quads_are_split_along_their_most_occluded_diagonal() {
+        let registry = BlockInfoRegistry::for_tests();
+        let dirt = registry.get_block_info("potato_crust:dirt");
+        let mut block_data = ChunkBlockData::default();
+
+        // Block diagonally above the top face's +X +Z corner
+        block_data.set(UVec3::new(3, 4, 5), dirt.id);
+        block_data.set(UVec3::new(4, 5, 6), dirt.id);
+
+        let mesh = ChunkVoxelData::from_block_data(&block_data, &registry).to_render_meshes(&ChunkLightVolume::default(), &registry).opaque;
+        let (Some(VertexAttributeValues::Float32x3(positions)), Some(VertexAttributeValues::Float32x3(normals)), Some(VertexAttributeValues::Float32(occlusion)), Some(Indices::U32(indices))) = (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+            mesh.attribute(ATTRIBUTE_AMBIENT_OCCLUSION),
+            mesh.indices(),
+        ) else {
+            panic!("mesh has no vertex positions, normals, ambient occlusion or indices");
+        };
+
+        let occluded_vertices = (0..positions.len())
+            .filter(|i| positions[*i][1] == 5.0 && normals[*i] == [0.0, 1.0, 0.0] && occlusion[*i] > 0.0)
+            .collect::<Vec<_>>();
+        assert_eq!(occluded_vertices.len(), 1, "only the top face's corner is occluded");
+        assert_eq!(positions[occluded_vertices[0]], [4.0, 5.0, 6.0]);
+        assert_eq!(occlusion[occluded_vertices[0]], 1.0);
+
+        // Both triangles of the quad meet at the occluded corner
+        let quad_start = occluded_vertices[0] as u32 / 4 * 4;
+        let quad_triangles = indices.chunks_exact(3).filter(|triangle| triangle[0] / 4 * 4 == quad_start).collect::<Vec<_>>();
+        assert_eq!(quad_triangles.len(), 2);
+        assert!(quad_triangles.iter().all(|triangle| triangle.contains(&(occluded_vertices[0] as u32))));
+    }
+
+    #[test]
+    fn paletted_block_data_takes_a_fraction_of_the_uncompressed_size() {
+        let registry = BlockInfoRegistry::for_tests();
+        let generator = HeightmapWorldGenerator::default();
+        let uncompressed_size = ChunkBlockShape::USIZE * std::mem::size_of::<BlockId>();
+
+        let air = ChunkBlockData::default();
+        let stone = ChunkBlockData::filled(registry.get_block_info("potato_crust:cobblestone").id);
+
+        // Uniform chunks get by without any indices
+        assert_eq!(air.memory_usage(), std::mem::size_of::<ChunkBlockData>());
+        assert_eq!(stone.memory_usage(), std::mem::size_of::<ChunkBlockData>());
+
+        // Surface chunks hold air, grass, dirt & cobblestone: 2 bits per block instead of a 16-bit ID
+        let positions = (-4..4).flat_map(|x| (-4..4).map(move |z| IVec3::new(x, 0, z)));
+        let (chunk_count, total_size) = positions.fold((0, 0), |(count, size), position| {
+            let block_data = generator.generate_chunk(DEFAULT_WORLD_SEED, &ChunkPosition(position), &registry);
+            (count + 1, size + block_data.memory_usage())
+        });
+        let average_size = total_size / chunk_count;
+
+        assert!(average_size <= uncompressed_size / 7, "generated chunks take {average_size} bytes on average");
+    }
+}
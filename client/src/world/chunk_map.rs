@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::block_info::{BlockId, BlockInfo, BlockInfoRegistry};
+use crate::world::chunk::{split_block_position, ChunkBlockData};
+
+/// Index of chunk entities by chunk position, covering chunks that are still being generated too.
+///
+/// Kept in sync by the chunk spawn & despawn handlers, so nothing else should insert or remove entries.
+#[derive(Debug, Default, Resource)]
+pub struct ChunkMap {
+    chunks: HashMap<IVec3, Entity>,
+}
+
+impl ChunkMap {
+    pub fn get(&self, chunk_position: IVec3) -> Option<Entity> {
+        self.chunks.get(&chunk_position).copied()
+    }
+
+    pub fn insert(&mut self, chunk_position: IVec3, entity: Entity) -> Option<Entity> {
+        self.chunks.insert(chunk_position, entity)
+    }
+
+    pub fn remove(&mut self, chunk_position: IVec3) -> Option<Entity> {
+        self.chunks.remove(&chunk_position)
+    }
+}
+
+/// World-space access to the blocks of loaded chunks. Read-only, so systems that only look at blocks can run side by
+/// side; see `WorldBlocksMut` for editing them.
+#[derive(SystemParam)]
+pub struct WorldBlocks<'w, 's> {
+    chunk_map: Res<'w, ChunkMap>,
+    block_info_registry: Res<'w, BlockInfoRegistry>,
+    chunks: Query<'w, 's, &'static ChunkBlockData>,
+}
+
+impl<'w, 's> WorldBlocks<'w, 's> {
+    /// Block data of the chunk at `chunk_position`, if it's loaded and done generating
+    pub fn chunk(&self, chunk_position: IVec3) -> Option<&ChunkBlockData> {
+        self.chunks.get(self.chunk_map.get(chunk_position)?).ok()
+    }
+
+    pub fn chunk_entity(&self, chunk_position: IVec3) -> Option<Entity> {
+        self.chunk_map.get(chunk_position)
+    }
+
+    /// Block at a world-space position, `None` for air and for positions in chunks that aren't loaded
+    pub fn get_block(&self, block_position: IVec3) -> Option<&Arc<BlockInfo>> {
+        let (chunk_position, local_position) = split_block_position(block_position);
+
+        self.block_info_registry.resolve_block_id(self.chunk(chunk_position)?.get(local_position))
+    }
+}
+
+/// World-space access to the blocks of loaded chunks, for systems that edit them
+#[derive(SystemParam)]
+pub struct WorldBlocksMut<'w, 's> {
+    chunk_map: Res<'w, ChunkMap>,
+    block_info_registry: Res<'w, BlockInfoRegistry>,
+    chunks: Query<'w, 's, &'static mut ChunkBlockData>,
+}
+
+impl<'w, 's> WorldBlocksMut<'w, 's> {
+    /// Read-only view of the same blocks, for lookups in between edits
+    pub fn as_readonly(&self) -> WorldBlocks<'_, 's> {
+        WorldBlocks {
+            chunk_map: Res::clone(&self.chunk_map),
+            block_info_registry: Res::clone(&self.block_info_registry),
+            chunks: self.chunks.to_readonly(),
+        }
+    }
+
+    /// Replaces the block at a world-space position, returns `false` if its chunk isn't loaded
+    pub fn set_block(&mut self, block_position: IVec3, block_id: BlockId) -> bool {
+        let (chunk_position, local_position) = split_block_position(block_position);
+
+        let Some(mut block_data) = self
+            .chunk_map
+            .get(chunk_position)
+            .and_then(|entity| self.chunks.get_mut(entity).ok())
+        else {
+            return false;
+        };
+
+        block_data.set(local_position, block_id);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+    use crate::block_info::AIR_BLOCK_ID;
+
+    #[test]
+    fn world_blocks_resolve_chunks_across_negative_coordinates() {
+        let registry = BlockInfoRegistry::for_tests();
+        let mut world = World::new();
+        let mut chunk_map = ChunkMap::default();
+
+        for chunk_position in [IVec3::ZERO, IVec3::new(-1, 0, -1)] {
+            let entity = world.spawn(ChunkBlockData::default()).id();
+            chunk_map.insert(chunk_position, entity);
+        }
+        world.insert_resource(chunk_map);
+        world.insert_resource(registry.clone());
+
+        let mut state = SystemState::<WorldBlocksMut>::new(&mut world);
+        let mut world_blocks_mut = state.get_mut(&mut world);
+
+        let dirt = registry.get_block_info("potato_crust:dirt");
+
+        assert!(world_blocks_mut.set_block(IVec3::new(-1, 3, -16), dirt.id));
+        assert!(!world_blocks_mut.set_block(IVec3::new(16, 0, 0), AIR_BLOCK_ID), "chunk (1, 0, 0) is not loaded");
+
+        let world_blocks = world_blocks_mut.as_readonly();
+
+        assert_eq!(world_blocks.get_block(IVec3::new(-1, 3, -16)), Some(&dirt));
+        assert!(world_blocks.get_block(IVec3::new(0, 3, -16)).is_none());
+        assert!(world_blocks.get_block(IVec3::new(-1, 3, -17)).is_none());
+
+        let local_block = world_blocks
+            .chunk(IVec3::new(-1, 0, -1))
+            .map(|block_data| block_data.get(UVec3::new(15, 3, 0)));
+        assert_eq!(local_block, Some(dirt.id), "block landed in the wrong chunk-local slot");
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+
+/// Keeps boxes from snagging on voxels they merely touch, as they end up exactly on block boundaries
+const COLLISION_EPSILON: f32 = 1e-4;
+
+/// Axis-aligned bounding box in world space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Box standing on `feet_position`, centered on it horizontally
+    pub fn from_feet(feet_position: Vec3, width: f32, height: f32) -> Self {
+        let half_width = width / 2.0;
+
+        Self {
+            min: feet_position - Vec3::new(half_width, 0.0, half_width),
+            max: feet_position + Vec3::new(half_width, height, half_width),
+        }
+    }
+
+    pub fn feet_position(&self) -> Vec3 {
+        Vec3::new((self.min.x + self.max.x) / 2.0, self.min.y, (self.min.z + self.max.z) / 2.0)
+    }
+
+    pub fn translated(&self, offset: Vec3) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    /// Blocks the box overlaps on `axis`, not counting the ones it only touches
+    fn block_range(&self, axis: usize) -> std::ops::RangeInclusive<i32> {
+        (self.min[axis] + COLLISION_EPSILON).floor() as i32..=(self.max[axis] - COLLISION_EPSILON).floor() as i32
+    }
+
+    /// Whether the box overlaps any solid block
+    pub fn intersects_solid(&self, is_solid: &impl Fn(IVec3) -> bool) -> bool {
+        self.block_range(0).any(|x| self.block_range(1).any(|y| self.block_range(2).any(|z| is_solid(IVec3::new(x, y, z)))))
+    }
+}
+
+/// How far the box gets moving `distance` along `axis` before running into a solid block.
+///
+/// Only blocks ahead of the box are checked, one layer at a time, so even fast moving boxes can't tunnel
+/// through thin walls. Blocks the box already overlaps are ignored, letting it move out of them.
+pub fn sweep_axis(aabb: &Aabb, axis: usize, distance: f32, is_solid: &impl Fn(IVec3) -> bool) -> f32 {
+    if distance == 0.0 {
+        return 0.0;
+    }
+
+    let [other_a, other_b] = [(axis + 1) % 3, (axis + 2) % 3];
+    let is_layer_solid = |layer: i32| {
+        aabb.block_range(other_a).any(|a| {
+            aabb.block_range(other_b).any(|b| {
+                let mut block_position = IVec3::ZERO;
+                block_position[axis] = layer;
+                block_position[other_a] = a;
+                block_position[other_b] = b;
+
+                is_solid(block_position)
+            })
+        })
+    };
+
+    match distance > 0.0 {
+        true => {
+            let leading_face = aabb.max[axis];
+            let mut layer = (leading_face - COLLISION_EPSILON).ceil() as i32;
+
+            while (layer as f32) < leading_face + distance {
+                if is_layer_solid(layer) {
+                    return (layer as f32 - leading_face).max(0.0);
+                }
+
+                layer += 1;
+            }
+        }
+        false => {
+            let leading_face = aabb.min[axis];
+            let mut layer = (leading_face + COLLISION_EPSILON).floor() as i32 - 1;
+
+            while (layer + 1) as f32 > leading_face + distance {
+                if is_layer_solid(layer) {
+                    return ((layer + 1) as f32 - leading_face).min(0.0);
+                }
+
+                layer -= 1;
+            }
+        }
+    }
+
+    distance
+}
+
+/// Moves the box by `offset` one axis at a time, vertical first, stopping short of solid blocks on each.
+/// Returns the moved box & the offset it actually got moved by.
+pub fn move_and_collide(aabb: &Aabb, offset: Vec3, is_solid: &impl Fn(IVec3) -> bool) -> (Aabb, Vec3) {
+    let mut moved = *aabb;
+    let mut applied_offset = Vec3::ZERO;
+
+    for axis in [1, 0, 2] {
+        applied_offset[axis] = sweep_axis(&moved, axis, offset[axis], is_solid);
+
+        let mut axis_offset = Vec3::ZERO;
+        axis_offset[axis] = applied_offset[axis];
+        moved = moved.translated(axis_offset);
+    }
+
+    (moved, applied_offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floor(block_position: IVec3) -> bool {
+        block_position.y < 0
+    }
+
+    #[test]
+    fn boxes_land_on_the_floor_without_tunnelling() {
+        let player = Aabb::from_feet(Vec3::new(0.5, 3.25, 0.5), 0.6, 1.8);
+
+        // Falling far further than the floor is thick in a single step
+        let (landed, offset) = move_and_collide(&player, Vec3::new(0.0, -100.0, 0.0), &|block_position| block_position.y == -1);
+        assert_eq!(offset, Vec3::new(0.0, -3.25, 0.0));
+        assert_eq!(landed.feet_position(), Vec3::new(0.5, 0.0, 0.5));
+
+        // Standing on the floor, nothing stops sideways movement
+        let (_, offset) = move_and_collide(&landed, Vec3::new(3.0, 0.0, -2.0), &floor);
+        assert_eq!(offset, Vec3::new(3.0, 0.0, -2.0));
+    }
+
+    #[test]
+    fn walls_stop_only_the_axis_running_into_them() {
+        let is_solid = |block_position: IVec3| floor(block_position) || block_position.x == 3;
+        let player = Aabb::from_feet(Vec3::new(1.5, 0.0, 0.5), 0.6, 1.8);
+
+        let (moved, offset) = move_and_collide(&player, Vec3::new(2.0, 0.0, 1.5), &is_solid);
+        assert!((offset.x - 1.2).abs() < 1e-5, "{offset}");
+        assert_eq!(offset.z, 1.5);
+        assert!((moved.max.x - 3.0).abs() < 1e-5);
+
+        // Touching the wall doesn't keep the box from sliding along it or backing off
+        assert_eq!(sweep_axis(&moved, 2, -4.0, &is_solid), -4.0);
+        assert_eq!(sweep_axis(&moved, 0, -1.0, &is_solid), -1.0);
+        assert!(sweep_axis(&moved, 0, 1.0, &is_solid).abs() < 1e-5);
+    }
+
+    #[test]
+    fn boxes_only_collide_with_blocks_they_overlap() {
+        // Pillar right next to the box's path, but not in it
+        let is_solid = |block_position: IVec3| block_position == IVec3::new(1, 1, 1);
+        let player = Aabb::from_feet(Vec3::new(0.5, 0.0, -2.0), 0.6, 1.8);
+
+        assert_eq!(sweep_axis(&player, 2, 5.0, &is_solid), 5.0);
+        assert!(!player.translated(Vec3::new(0.0, 0.0, 3.0)).intersects_solid(&is_solid));
+        assert!(player.translated(Vec3::new(0.6, 0.0, 3.0)).intersects_solid(&is_solid));
+
+        // Negative coordinates floor towards negative infinity
+        let is_solid = |block_position: IVec3| block_position == IVec3::new(-2, 0, -1);
+        let player = Aabb::from_feet(Vec3::new(0.5, 0.0, -0.5), 0.6, 1.8);
+        let distance = sweep_axis(&player, 0, -5.0, &is_solid);
+        assert!((distance + 1.2).abs() < 1e-5, "{distance}");
+    }
+}
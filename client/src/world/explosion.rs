@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::Deserialize;
+
+use crate::block_info::{BlockInfoRegistry, AIR_BLOCK_ID};
+use crate::world::chunk::{split_block_position, ChunkBlockData, CHUNK_SIZE};
+use crate::world::chunk_map::ChunkMap;
+use crate::world::systems::BlockUpdate;
+
+/// Explosives caught in another explosion's blast go off after this much shorter fuse
+pub const CHAIN_REACTION_FUSE_SECONDS: f32 = 0.5;
+
+/// Blocks like TNT, which blow up once ignited & their fuse burns down
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct BlockExplosive {
+    /// Blocks whose centers are at most this many blocks away from the explosive's center get removed
+    pub radius: f32,
+    pub fuse_seconds: f32,
+}
+
+impl BlockExplosive {
+    pub fn is_valid(&self) -> bool {
+        self.radius > 0.0 && self.radius <= CHUNK_SIZE as f32 && self.fuse_seconds >= 0.0
+    }
+
+    /// Offsets from the explosive of every block within its blast radius
+    fn blast_offsets(&self) -> impl Iterator<Item = IVec3> {
+        let reach = self.radius.floor() as i32;
+        let radius_squared = self.radius * self.radius;
+
+        (-reach..=reach)
+            .flat_map(move |x| (-reach..=reach).flat_map(move |y| (-reach..=reach).map(move |z| IVec3::new(x, y, z))))
+            .filter(move |offset| offset.as_vec3().length_squared() <= radius_squared)
+    }
+}
+
+/// Explosives whose fuses are burning, by world-space block position
+#[derive(Debug, Default, Resource)]
+pub struct PrimedExplosives {
+    fuses: HashMap<IVec3, Timer>,
+}
+
+impl PrimedExplosives {
+    /// Lights the fuse of the explosive at `block_position`, returns `false` if it's already burning
+    pub fn ignite(&mut self, block_position: IVec3, fuse_seconds: f32) -> bool {
+        if self.fuses.contains_key(&block_position) {
+            return false;
+        }
+
+        self.fuses.insert(block_position, Timer::from_seconds(fuse_seconds, TimerMode::Once));
+
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fuses.is_empty()
+    }
+
+    /// Burns down every fuse, returning the positions of the explosives going off
+    pub fn tick(&mut self, delta: Duration) -> Vec<IVec3> {
+        let mut detonating = vec![];
+
+        self.fuses.retain(|block_position, fuse| {
+            let is_burnt_down = fuse.tick(delta).finished();
+
+            if is_burnt_down {
+                detonating.push(*block_position);
+            }
+
+            !is_burnt_down
+        });
+
+        detonating
+    }
+}
+
+/// Blocks removed by detonating a batch of explosives
+#[derive(Debug, Default)]
+pub struct Detonation {
+    pub removed_blocks: Vec<IVec3>,
+    /// Chunks that had blocks removed, each one edited & compacted just once however many explosions hit it
+    pub changed_chunks: HashSet<IVec3>,
+}
+
+/// Blocks within the blast radius of a batch of explosives, grouped by chunk so every chunk gets edited in one go
+#[derive(Debug, Default)]
+pub struct Blast {
+    detonating: HashSet<IVec3>,
+    blocks_by_chunk: HashMap<IVec3, HashSet<UVec3>>,
+}
+
+impl Blast {
+    pub fn new(explosions: &[(IVec3, BlockExplosive)]) -> Self {
+        let mut blast = Self {
+            detonating: explosions.iter().map(|(block_position, _)| *block_position).collect(),
+            ..Default::default()
+        };
+
+        for (block_position, explosive) in explosions {
+            for offset in explosive.blast_offsets() {
+                let (chunk_position, local_position) = split_block_position(*block_position + offset);
+                blast.blocks_by_chunk.entry(chunk_position).or_default().insert(local_position);
+            }
+        }
+
+        blast
+    }
+
+    /// Chunks the blast reaches into
+    pub fn chunks(&self) -> impl Iterator<Item = IVec3> + '_ {
+        self.blocks_by_chunk.keys().copied()
+    }
+
+    /// Removes the blocks of a chunk within the blast, compacting it afterwards, and returns their world-space
+    /// positions. Explosives caught in the blast aren't removed, but ignited with a short fuse instead, to go off
+    /// in a chain reaction.
+    pub fn detonate_chunk(
+        &self,
+        chunk_position: IVec3,
+        block_data: &mut ChunkBlockData,
+        block_info_registry: &BlockInfoRegistry,
+        primed_explosives: &mut PrimedExplosives,
+    ) -> Vec<IVec3> {
+        let Some(local_positions) = self.blocks_by_chunk.get(&chunk_position) else {
+            return vec![];
+        };
+
+        let chunk_origin = chunk_position * CHUNK_SIZE as i32;
+        let mut removed_blocks = vec![];
+
+        for local_position in local_positions {
+            let block_position = chunk_origin + local_position.as_ivec3();
+
+            let Some(block_info) = block_info_registry.resolve_block_id(block_data.get(*local_position)) else {
+                continue;
+            };
+
+            if block_info.explosive.is_some() && !self.detonating.contains(&block_position) {
+                primed_explosives.ignite(block_position, CHAIN_REACTION_FUSE_SECONDS);
+                continue;
+            }
+
+            block_data.set(*local_position, AIR_BLOCK_ID);
+            removed_blocks.push(block_position);
+        }
+
+        if !removed_blocks.is_empty() {
+            block_data.compact();
+        }
+
+        removed_blocks
+    }
+}
+
+/// Detonates explosives whose fuse burnt down. Explosives that got broken while their fuse was burning are duds.
+pub fn detonate_primed_explosives(
+    time: Res<Time>,
+    mut primed_explosives: ResMut<PrimedExplosives>,
+    chunk_map: Res<ChunkMap>,
+    block_info_registry: Res<BlockInfoRegistry>,
+    mut chunks: Query<&mut ChunkBlockData>,
+    mut block_updates: EventWriter<BlockUpdate>,
+) {
+    if primed_explosives.is_empty() {
+        return;
+    }
+
+    let explosions = primed_explosives
+        .tick(time.delta())
+        .into_iter()
+        .filter_map(|block_position| {
+            let (chunk_position, local_position) = split_block_position(block_position);
+            let block_data = chunks.get(chunk_map.get(chunk_position)?).ok()?;
+
+            Some((block_position, block_info_registry.resolve_block_id(block_data.get(local_position))?.explosive?))
+        })
+        .collect::<Vec<_>>();
+
+    if explosions.is_empty() {
+        return;
+    }
+
+    let blast = Blast::new(&explosions);
+    let mut detonation = Detonation::default();
+
+    for chunk_position in blast.chunks() {
+        let Some(mut block_data) = chunk_map.get(chunk_position).and_then(|entity| chunks.get_mut(entity).ok()) else {
+            continue;
+        };
+
+        // Only chunks that actually lost blocks count as changed, not every chunk the blast reaches into
+        let removed_blocks = blast.detonate_chunk(chunk_position, block_data.bypass_change_detection(), &block_info_registry, &mut primed_explosives);
+
+        if !removed_blocks.is_empty() {
+            block_data.set_changed();
+            detonation.changed_chunks.insert(chunk_position);
+            detonation.removed_blocks.extend(removed_blocks);
+        }
+    }
+
+    debug!(
+        "{} explosions removed {} blocks from {} chunks",
+        explosions.len(),
+        detonation.removed_blocks.len(),
+        detonation.changed_chunks.len()
+    );
+
+    block_updates.send_batch(detonation.removed_blocks.into_iter().map(BlockUpdate::new));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block(chunks: &HashMap<IVec3, ChunkBlockData>, registry: &BlockInfoRegistry, block_position: IVec3) -> Option<String> {
+        let (chunk_position, local_position) = split_block_position(block_position);
+
+        registry.resolve_block_id(chunks[&chunk_position].get(local_position)).map(|block_info| block_info.get_state_name())
+    }
+
+    // Ticks the fuses & detonates whatever goes off, like `detonate_primed_explosives` does
+    fn tick(chunks: &mut HashMap<IVec3, ChunkBlockData>, registry: &BlockInfoRegistry, primed_explosives: &mut PrimedExplosives, delta: Duration) -> Detonation {
+        let explosions = primed_explosives
+            .tick(delta)
+            .into_iter()
+            .filter_map(|block_position| {
+                let (chunk_position, local_position) = split_block_position(block_position);
+                Some((block_position, registry.resolve_block_id(chunks.get(&chunk_position)?.get(local_position))?.explosive?))
+            })
+            .collect::<Vec<_>>();
+
+        let blast = Blast::new(&explosions);
+        let mut detonation = Detonation::default();
+
+        for chunk_position in blast.chunks() {
+            let Some(block_data) = chunks.get_mut(&chunk_position) else {
+                continue;
+            };
+
+            let removed_blocks = blast.detonate_chunk(chunk_position, block_data, registry, primed_explosives);
+
+            if !removed_blocks.is_empty() {
+                detonation.changed_chunks.insert(chunk_position);
+                detonation.removed_blocks.extend(removed_blocks);
+            }
+        }
+
+        detonation
+    }
+
+    #[test]
+    fn explosions_carve_spheres_and_chain_react() {
+        let registry = BlockInfoRegistry::for_tests();
+        let tnt = registry.get_block_info("potato_crust:tnt");
+        let explosive = tnt.explosive.expect("TNT is explosive");
+
+        let mut block_data = ChunkBlockData::filled(registry.get_block_info("potato_crust:dirt").id);
+        block_data.set(UVec3::new(8, 8, 8), tnt.id);
+        block_data.set(UVec3::new(11, 8, 8), tnt.id);
+        let mut chunks = HashMap::from([(IVec3::ZERO, block_data)]);
+
+        let mut primed_explosives = PrimedExplosives::default();
+        assert!(primed_explosives.ignite(IVec3::new(8, 8, 8), explosive.fuse_seconds));
+        assert!(!primed_explosives.ignite(IVec3::new(8, 8, 8), explosive.fuse_seconds));
+
+        let detonation = tick(&mut chunks, &registry, &mut primed_explosives, Duration::from_secs_f32(explosive.fuse_seconds / 2.0));
+        assert!(detonation.removed_blocks.is_empty(), "went off before the fuse burnt down");
+
+        let detonation = tick(&mut chunks, &registry, &mut primed_explosives, Duration::from_secs_f32(explosive.fuse_seconds / 2.0));
+        let sphere_volume = explosive.blast_offsets().count();
+
+        // Everything within the radius goes, except for the other TNT, which got ignited instead
+        assert_eq!(detonation.removed_blocks.len(), sphere_volume - 1);
+        assert_eq!(detonation.changed_chunks, HashSet::from([IVec3::ZERO]));
+        assert_eq!(get_block(&chunks, &registry, IVec3::new(8, 8, 8)), None);
+        assert_eq!(get_block(&chunks, &registry, IVec3::new(8, 8, 12)), None);
+        assert_eq!(get_block(&chunks, &registry, IVec3::new(8, 8, 13)).as_deref(), Some("potato_crust:dirt"));
+        assert_eq!(get_block(&chunks, &registry, IVec3::new(11, 8, 8)).as_deref(), Some("potato_crust:tnt"));
+        assert!(!primed_explosives.is_empty());
+
+        let detonation = tick(&mut chunks, &registry, &mut primed_explosives, Duration::from_secs_f32(CHAIN_REACTION_FUSE_SECONDS));
+        assert!(detonation.removed_blocks.contains(&IVec3::new(11, 8, 8)));
+        assert_eq!(get_block(&chunks, &registry, IVec3::new(15, 8, 8)), None);
+        assert!(primed_explosives.is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::block_info::BlockInfoRegistry;
+use crate::world::chunk::{ChunkBlockData, ChunkPosition, CHUNK_SIZE};
+
+pub const DEFAULT_WORLD_SEED: u64 = 0x5EED_C0FF_EE00_0001;
+
+/// Produces the initial block contents of a chunk.
+///
+/// Implementations must be deterministic: the same seed and chunk position have to yield the same
+/// block data every time, so chunks can be thrown away and regenerated at will.
+pub trait WorldGenerator: Send + Sync {
+    fn generate_chunk(
+        &self,
+        seed: u64,
+        chunk_position: &ChunkPosition,
+        block_info_registry: &BlockInfoRegistry,
+    ) -> ChunkBlockData;
+}
+
+#[derive(Clone, Resource)]
+pub struct WorldGeneratorSettings {
+    pub seed: u64,
+    pub generator: Arc<dyn WorldGenerator>,
+}
+
+impl Default for WorldGeneratorSettings {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_WORLD_SEED,
+            generator: Arc::new(HeightmapWorldGenerator::default()),
+        }
+    }
+}
+
+/// Rolling hills made out of fractal value noise: grass on top, a few layers of dirt below it,
+/// cobblestone all the way down.
+#[derive(Clone, Copy, Debug)]
+pub struct HeightmapWorldGenerator {
+    pub base_height: i32,
+    pub height_variation: f32,
+    /// Size of the biggest noise feature, in blocks
+    pub feature_size: f32,
+    pub octaves: u32,
+    pub dirt_depth: i32,
+}
+
+impl Default for HeightmapWorldGenerator {
+    fn default() -> Self {
+        Self {
+            base_height: 8,
+            height_variation: 6.0,
+            feature_size: 48.0,
+            octaves: 4,
+            dirt_depth: 3,
+        }
+    }
+}
+
+impl HeightmapWorldGenerator {
+    /// World-space Y of the topmost solid block of the column at `x`, `z`.
+    pub fn surface_height(&self, seed: u64, x: i32, z: i32) -> i32 {
+        let noise = fractal_noise_2d(
+            seed,
+            x as f32 / self.feature_size,
+            z as f32 / self.feature_size,
+            self.octaves,
+        );
+
+        self.base_height + (noise * self.height_variation).round() as i32
+    }
+}
+
+impl WorldGenerator for HeightmapWorldGenerator {
+    fn generate_chunk(
+        &self,
+        seed: u64,
+        chunk_position: &ChunkPosition,
+        block_info_registry: &BlockInfoRegistry,
+    ) -> ChunkBlockData {
+        let grass = block_info_registry.get_block_info("potato_crust:grass");
+        let dirt = block_info_registry.get_block_info("potato_crust:dirt");
+        let cobblestone = block_info_registry.get_block_info("potato_crust:cobblestone");
+
+        let chunk_origin = chunk_position.0 * CHUNK_SIZE as i32;
+        let mut block_data = ChunkBlockData::default();
+
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let world_x = chunk_origin.x + x as i32;
+                let world_z = chunk_origin.z + z as i32;
+                let surface_height = self.surface_height(seed, world_x, world_z);
+
+                for y in 0..CHUNK_SIZE {
+                    let world_y = chunk_origin.y + y as i32;
+
+                    let block_info = match world_y {
+                        _ if world_y > surface_height => continue,
+                        _ if world_y == surface_height => &grass,
+                        _ if world_y >= surface_height - self.dirt_depth => &dirt,
+                        _ => &cobblestone,
+                    };
+
+                    block_data.set(UVec3::new(x, y, z), block_info.id);
+                }
+            }
+        }
+
+        block_data.compact();
+        block_data
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Pseudo-random value in `[-1.0, 1.0]` attached to an integer lattice point.
+fn lattice_value(seed: u64, x: i32, z: i32) -> f32 {
+    let hash = splitmix64(seed ^ splitmix64(((x as u32 as u64) << 32) | z as u32 as u64));
+
+    (hash >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn value_noise_2d(seed: u64, x: f32, z: f32) -> f32 {
+    let x0 = x.floor();
+    let z0 = z.floor();
+    let tx = smoothstep(x - x0);
+    let tz = smoothstep(z - z0);
+    let (x0, z0) = (x0 as i32, z0 as i32);
+
+    let v00 = lattice_value(seed, x0, z0);
+    let v10 = lattice_value(seed, x0 + 1, z0);
+    let v01 = lattice_value(seed, x0, z0 + 1);
+    let v11 = lattice_value(seed, x0 + 1, z0 + 1);
+
+    let near = v00 + (v10 - v00) * tx;
+    let far = v01 + (v11 - v01) * tx;
+
+    near + (far - near) * tz
+}
+
+/// Sum of `octaves` layers of value noise, each with double the frequency and half the amplitude
+/// of the previous one, normalized back to `[-1.0, 1.0]`.
+fn fractal_noise_2d(seed: u64, x: f32, z: f32, octaves: u32) -> f32 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut max_amplitude = 0.0;
+
+    for octave in 0..octaves.max(1) {
+        let octave_seed = splitmix64(seed.wrapping_add(octave as u64));
+        total += value_noise_2d(octave_seed, x * frequency, z * frequency) * amplitude;
+        max_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    total / max_amplitude
+}
+
+#[cfg(test)]
+mod tests {
+    use block_mesh::ndshape::ConstShape;
+
+    use super::*;
+    use crate::world::chunk::ChunkBlockShape;
+
+    fn block_name_at(block_data: &ChunkBlockData, registry: &BlockInfoRegistry, x: u32, y: u32, z: u32) -> Option<String> {
+        registry
+            .resolve_block_id(block_data.get(UVec3::new(x, y, z)))
+            .map(|info| info.get_registry_name())
+    }
+
+    #[test]
+    fn generation_is_deterministic_per_seed() {
+        let registry = BlockInfoRegistry::for_tests();
+        let generator = HeightmapWorldGenerator::default();
+        let chunk_position = ChunkPosition(IVec3::new(-3, 0, 7));
+
+        let first = generator.generate_chunk(42, &chunk_position, &registry);
+        let second = generator.generate_chunk(42, &chunk_position, &registry);
+        let other_seed = generator.generate_chunk(43, &chunk_position, &registry);
+
+        let names = |data: &ChunkBlockData| {
+            (0..ChunkBlockShape::SIZE)
+                .map(|i| {
+                    let [x, y, z] = ChunkBlockShape::delinearize(i);
+                    block_name_at(data, &registry, x, y, z)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&first), names(&second), "same seed produced different chunks");
+        assert_ne!(names(&first), names(&other_seed), "different seeds produced identical chunks");
+    }
+
+    #[test]
+    fn columns_are_layered_around_surface_height() {
+        let registry = BlockInfoRegistry::for_tests();
+        let generator = HeightmapWorldGenerator::default();
+        let seed = 1234;
+        let block_data = generator.generate_chunk(seed, &ChunkPosition(IVec3::ZERO), &registry);
+
+        for (x, z) in [(0, 0), (5, 11), (15, 15)] {
+            let surface_height = generator.surface_height(seed, x as i32, z as i32);
+            assert!((0..CHUNK_SIZE as i32).contains(&surface_height));
+
+            for y in 0..CHUNK_SIZE {
+                let expected = match y as i32 {
+                    y if y > surface_height => None,
+                    y if y == surface_height => Some("potato_crust:grass"),
+                    y if y >= surface_height - generator.dirt_depth => Some("potato_crust:dirt"),
+                    _ => Some("potato_crust:cobblestone"),
+                };
+
+                assert_eq!(
+                    block_name_at(&block_data, &registry, x, y, z).as_deref(),
+                    expected,
+                    "unexpected block at ({x}, {y}, {z})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn surface_height_is_fixed_for_known_seed() {
+        let generator = HeightmapWorldGenerator::default();
+        let heights = [(0, 0), (100, -37), (-512, 2048), (7, 7)]
+            .map(|(x, z)| generator.surface_height(DEFAULT_WORLD_SEED, x, z));
+
+        assert_eq!(heights, [5, 11, 8, 7]);
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::block_info::{BlockInfo, BlockInfoRegistry, BlockSide, AIR_BLOCK_ID};
+use crate::block_state::BlockPropertyValue;
+use crate::character_controller::CharacterController;
+use crate::inventory::Inventory;
+use crate::player::Player;
+use crate::world::chunk::split_block_position;
+use crate::world::chunk_map::{WorldBlocks, WorldBlocksMut};
+use crate::world::explosion::PrimedExplosives;
+use crate::world::raycast::raycast_voxels;
+use crate::world::systems::BlockUpdate;
+
+#[derive(Resource)]
+pub struct BlockInteractionSettings {
+    pub reach_distance: f32,
+    pub mouse_key_break: MouseButton,
+    pub mouse_key_place: MouseButton,
+}
+
+impl Default for BlockInteractionSettings {
+    fn default() -> Self {
+        Self {
+            reach_distance: 6.0,
+            mouse_key_break: MouseButton::Left,
+            mouse_key_place: MouseButton::Right,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockRaycastHit {
+    pub block: Arc<BlockInfo>,
+    pub chunk: Entity,
+    pub chunk_position: IVec3,
+    /// World-space position of the hit block
+    pub block_position: IVec3,
+    /// Face of the block the ray entered through
+    pub side: BlockSide,
+    pub distance: f32,
+}
+
+/// Casts a ray through loaded chunks, returning the first non-air block it runs into
+pub fn raycast_blocks(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    world_blocks: &WorldBlocks,
+) -> Option<BlockRaycastHit> {
+    let hit = raycast_voxels(origin, direction, max_distance, |block_position| {
+        world_blocks.get_block(block_position).is_some()
+    })?;
+    let block = world_blocks.get_block(hit.block_position)?.clone();
+    let chunk_position = split_block_position(hit.block_position).0;
+
+    Some(BlockRaycastHit {
+        block,
+        chunk: world_blocks.chunk_entity(chunk_position)?,
+        chunk_position,
+        block_position: hit.block_position,
+        side: hit.side,
+        distance: hit.distance,
+    })
+}
+
+// Breaks the block the player is looking at into the inventory, or places the selected hotbar block against
+// the face being looked at. Explosives get ignited instead of having blocks placed against them.
+pub fn handle_block_interaction(
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    settings: Res<BlockInteractionSettings>,
+    block_info_registry: Res<BlockInfoRegistry>,
+    mut query_player: Query<(&Transform, &CharacterController, &mut Inventory), With<Player>>,
+    mut world_blocks: WorldBlocksMut,
+    mut primed_explosives: ResMut<PrimedExplosives>,
+    mut block_updates: EventWriter<BlockUpdate>,
+) {
+    let is_breaking = mouse_button_input.just_pressed(settings.mouse_key_break);
+    let is_placing = mouse_button_input.just_pressed(settings.mouse_key_place);
+
+    if !is_breaking && !is_placing {
+        return;
+    }
+
+    let Ok((player_transform, character_controller, mut inventory)) = query_player.get_single_mut() else {
+        return;
+    };
+
+    let Some(hit) = raycast_blocks(
+        player_transform.translation,
+        *player_transform.forward(),
+        settings.reach_distance,
+        &world_blocks.as_readonly(),
+    ) else {
+        return;
+    };
+
+    debug!(
+        "Looking at {:?} at {} in chunk {} ({:?}, {:?} face, {:.2} blocks away)",
+        hit.block.get_state_name(),
+        hit.block_position,
+        hit.chunk_position,
+        hit.chunk,
+        hit.side,
+        hit.distance,
+    );
+
+    if is_breaking {
+        // Blocks are picked up in their default state, so e.g. logs of every axis stack together. Placeholders
+        // for unknown blocks can be broken, but aren't worth carrying around.
+        let pickup = match Arc::ptr_eq(&hit.block, &block_info_registry.missing_block_info()) {
+            true => None,
+            false => match block_info_registry.try_get_block_info(&hit.block.get_registry_name()) {
+                Ok(block_info) => Some(block_info),
+                Err(error) => {
+                    warn!("Can't pick up block: {}", error);
+                    None
+                }
+            },
+        };
+
+        // Blocks stay in place rather than vanish when there's no room for them
+        if let Some(block_info) = &pickup {
+            if !inventory.can_add(block_info) {
+                debug!("No room in the inventory for {}", block_info.get_registry_name());
+                return;
+            }
+        }
+
+        if world_blocks.set_block(hit.block_position, AIR_BLOCK_ID) {
+            block_updates.send(BlockUpdate::new(hit.block_position));
+
+            if let Some(block_info) = pickup {
+                inventory.add(block_info, 1);
+            }
+        }
+    } else {
+        // Using an explosive lights its fuse rather than placing a block against it
+        if let Some(explosive) = hit.block.explosive {
+            if primed_explosives.ignite(hit.block_position, explosive.fuse_seconds) {
+                info!("Ignited {} at {}", hit.block.get_state_name(), hit.block_position);
+            }
+
+            return;
+        }
+
+        let block_position = hit.block_position + hit.side.normal();
+
+        if world_blocks.as_readonly().get_block(block_position).is_some() {
+            return;
+        }
+
+        let Some(block_info) = inventory.selected().map(|stack| stack.block.clone()) else {
+            return;
+        };
+
+        // Blocks with an axis, like logs, line up with the face they're placed against
+        let axis = match hit.side {
+            BlockSide::Left | BlockSide::Right => "x",
+            BlockSide::Top | BlockSide::Bottom => "y",
+            BlockSide::Front | BlockSide::Back => "z",
+        };
+        let block_info = block_info_registry
+            .try_get_block_state(&block_info, "axis", BlockPropertyValue::Enum(axis.to_string()))
+            .unwrap_or(block_info);
+
+        // Walking players can't place solid blocks inside themselves
+        let player_collider = character_controller.collider(player_transform.translation - character_controller.eye_offset());
+        if character_controller.enabled && block_info.is_solid() && player_collider.intersects_solid(&|position| position == block_position) {
+            return;
+        }
+
+        if world_blocks.set_block(block_position, block_info.id) {
+            inventory.take_selected();
+            block_updates.send(BlockUpdate::new(block_position));
+        }
+    }
+}
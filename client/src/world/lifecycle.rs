@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ChunkLifecycleState {
+    #[default]
+    Unloaded,
+    /// Requested, but no entity or generation task exists yet
+    Queued,
+    /// Entity exists, block data and/or the first mesh are still being built
+    Generating,
+    /// Block data is in place and the chunk got meshed at least once
+    Meshed,
+    /// Waiting to be despawned (and saved), remembers whether it was meshed in case the unload gets cancelled
+    Unloading { meshed: bool },
+}
+
+impl ChunkLifecycleState {
+    /// Whether the chunk is, or is about to be, part of the world
+    pub fn is_loaded(&self) -> bool {
+        matches!(self, Self::Queued | Self::Generating | Self::Meshed)
+    }
+}
+
+/// Lifecycle state of every chunk position that isn't `Unloaded`.
+///
+/// Load & unload requests only move positions between states, the spawn & despawn handlers then take whatever
+/// is still `Queued` or `Unloading`. That way a request that gets overridden before being handled (load
+/// followed by unload, or the other way around) cancels out, and duplicate requests collapse into one.
+#[derive(Debug, Default, Resource)]
+pub struct ChunkLifecycles {
+    states: HashMap<IVec3, ChunkLifecycleState>,
+    /// Positions in the order they got queued, may contain stale entries that got cancelled since
+    queued: Vec<IVec3>,
+    unloading: Vec<IVec3>,
+}
+
+impl ChunkLifecycles {
+    pub fn state(&self, chunk_position: IVec3) -> ChunkLifecycleState {
+        self.states.get(&chunk_position).copied().unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, ChunkLifecycleState)> + '_ {
+        self.states.iter().map(|(chunk_position, state)| (*chunk_position, *state))
+    }
+
+    pub fn request_load(&mut self, chunk_position: IVec3) {
+        match self.state(chunk_position) {
+            ChunkLifecycleState::Unloaded => {
+                self.states.insert(chunk_position, ChunkLifecycleState::Queued);
+                self.queued.push(chunk_position);
+            }
+            ChunkLifecycleState::Unloading { meshed } => {
+                let state = match meshed {
+                    true => ChunkLifecycleState::Meshed,
+                    false => ChunkLifecycleState::Generating,
+                };
+                self.states.insert(chunk_position, state);
+            }
+            ChunkLifecycleState::Queued | ChunkLifecycleState::Generating | ChunkLifecycleState::Meshed => {}
+        }
+    }
+
+    pub fn request_unload(&mut self, chunk_position: IVec3) {
+        match self.state(chunk_position) {
+            // Nothing got spawned yet, so there's nothing to despawn either
+            ChunkLifecycleState::Queued => {
+                self.states.remove(&chunk_position);
+            }
+            state @ (ChunkLifecycleState::Generating | ChunkLifecycleState::Meshed) => {
+                let meshed = state == ChunkLifecycleState::Meshed;
+                self.states.insert(chunk_position, ChunkLifecycleState::Unloading { meshed });
+                self.unloading.push(chunk_position);
+            }
+            ChunkLifecycleState::Unloaded | ChunkLifecycleState::Unloading { .. } => {}
+        }
+    }
+
+    /// Takes the positions still queued for loading, in request order, moving them to `Generating`
+    pub fn take_queued(&mut self) -> Vec<IVec3> {
+        let queued = std::mem::take(&mut self.queued);
+
+        queued
+            .into_iter()
+            .filter(|chunk_position| {
+                let is_queued = self.state(*chunk_position) == ChunkLifecycleState::Queued;
+
+                if is_queued {
+                    self.states.insert(*chunk_position, ChunkLifecycleState::Generating);
+                }
+
+                is_queued
+            })
+            .collect()
+    }
+
+    /// Takes the positions still waiting to be unloaded, moving them back to `Unloaded`
+    pub fn take_unloading(&mut self) -> Vec<IVec3> {
+        let unloading = std::mem::take(&mut self.unloading);
+
+        unloading
+            .into_iter()
+            .filter(|chunk_position| {
+                let is_unloading = matches!(self.state(*chunk_position), ChunkLifecycleState::Unloading { .. });
+
+                if is_unloading {
+                    self.states.remove(chunk_position);
+                }
+
+                is_unloading
+            })
+            .collect()
+    }
+
+    pub fn mark_meshed(&mut self, chunk_position: IVec3) {
+        let Some(state) = self.states.get_mut(&chunk_position) else {
+            return;
+        };
+
+        match state {
+            ChunkLifecycleState::Generating => *state = ChunkLifecycleState::Meshed,
+            ChunkLifecycleState::Unloading { meshed } => *meshed = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSITION: IVec3 = IVec3::new(3, 0, -2);
+
+    #[test]
+    fn duplicate_load_requests_queue_once() {
+        let mut lifecycles = ChunkLifecycles::default();
+
+        lifecycles.request_load(POSITION);
+        lifecycles.request_load(POSITION);
+
+        assert_eq!(lifecycles.take_queued(), vec![POSITION]);
+        assert_eq!(lifecycles.state(POSITION), ChunkLifecycleState::Generating);
+
+        lifecycles.request_load(POSITION);
+        assert!(lifecycles.take_queued().is_empty());
+    }
+
+    #[test]
+    fn unload_cancels_pending_load() {
+        let mut lifecycles = ChunkLifecycles::default();
+
+        lifecycles.request_load(POSITION);
+        lifecycles.request_unload(POSITION);
+
+        assert!(lifecycles.take_queued().is_empty());
+        assert!(lifecycles.take_unloading().is_empty());
+        assert_eq!(lifecycles.state(POSITION), ChunkLifecycleState::Unloaded);
+    }
+
+    #[test]
+    fn load_cancels_pending_unload() {
+        let mut lifecycles = ChunkLifecycles::default();
+
+        lifecycles.request_load(POSITION);
+        lifecycles.take_queued();
+        lifecycles.mark_meshed(POSITION);
+
+        lifecycles.request_unload(POSITION);
+        assert_eq!(lifecycles.state(POSITION), ChunkLifecycleState::Unloading { meshed: true });
+
+        lifecycles.request_load(POSITION);
+
+        assert!(lifecycles.take_unloading().is_empty());
+        assert!(lifecycles.take_queued().is_empty());
+        assert_eq!(lifecycles.state(POSITION), ChunkLifecycleState::Meshed);
+    }
+
+    #[test]
+    fn unload_while_generating_despawns_and_allows_reloading() {
+        let mut lifecycles = ChunkLifecycles::default();
+
+        lifecycles.request_load(POSITION);
+        lifecycles.take_queued();
+
+        lifecycles.request_unload(POSITION);
+        lifecycles.request_unload(POSITION);
+        // Mesh task finishing late doesn't bring the chunk back
+        lifecycles.mark_meshed(POSITION);
+
+        assert_eq!(lifecycles.take_unloading(), vec![POSITION]);
+        assert_eq!(lifecycles.state(POSITION), ChunkLifecycleState::Unloaded);
+
+        lifecycles.request_load(POSITION);
+        assert_eq!(lifecycles.take_queued(), vec![POSITION]);
+    }
+
+    #[test]
+    fn queued_positions_keep_request_order_without_duplicates() {
+        let mut lifecycles = ChunkLifecycles::default();
+        let positions = [IVec3::ZERO, IVec3::X, IVec3::NEG_Z, IVec3::Y];
+
+        for position in positions {
+            lifecycles.request_load(position);
+        }
+        lifecycles.request_unload(IVec3::X);
+        lifecycles.request_load(IVec3::X);
+
+        assert_eq!(lifecycles.take_queued(), vec![IVec3::ZERO, IVec3::X, IVec3::NEG_Z, IVec3::Y]);
+    }
+}
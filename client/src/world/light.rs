@@ -0,0 +1,670 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use block_mesh::ndshape::ConstShape;
+use block_mesh::{Voxel, VoxelVisibility};
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::block_info::{BlockInfo, BlockInfoRegistry, BlockSide};
+use crate::world::chunk::{split_block_position, ChunkBlockData, ChunkBlockShape, ChunkMeshDirty, ChunkPosition, ChunkVoxelShape, CHUNK_SIZE, CHUNK_SIZE_OUTER};
+use crate::world::chunk_map::ChunkMap;
+use crate::world::systems::BlockUpdate;
+use crate::world::voxel::{BlockVoxel, VoxelFace};
+
+pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+#[derive(Clone, Copy, Debug, EnumIter, Eq, PartialEq)]
+pub enum LightChannel {
+    /// Light coming down from the sky, which doesn't fade while going straight down
+    Sky,
+    /// Red, green & blue light given off by blocks, each spreading on its own
+    Red,
+    Green,
+    Blue,
+}
+
+impl LightChannel {
+    /// Bit offset of the channel within `VoxelLight`
+    fn shift(self) -> u16 {
+        self as u16 * 4
+    }
+}
+
+/// Light level of each channel at a single voxel, 4 bits each
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct VoxelLight(u16);
+
+impl VoxelLight {
+    /// Open sky, the light of voxels in chunks that aren't loaded
+    pub const SKY: Self = Self(MAX_LIGHT_LEVEL as u16);
+
+    pub fn get(self, channel: LightChannel) -> u8 {
+        (self.0 >> channel.shift() & 0xf) as u8
+    }
+
+    pub fn with(self, channel: LightChannel, level: u8) -> Self {
+        Self(self.0 & !(0xf << channel.shift()) | (level as u16) << channel.shift())
+    }
+
+    pub fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub fn to_bits(self) -> u16 {
+        self.0
+    }
+
+    /// Levels of each channel as a vertex attribute in `LightChannel` order, scaled to `0.0..=1.0`
+    pub fn to_vertex_light(self) -> [f32; 4] {
+        [LightChannel::Sky, LightChannel::Red, LightChannel::Green, LightChannel::Blue]
+            .map(|channel| self.get(channel) as f32 / MAX_LIGHT_LEVEL as f32)
+    }
+}
+
+/// Light of a chunk's voxels, in `ChunkBlockShape` order
+#[derive(Clone, Component, Debug)]
+pub struct ChunkLightData(Vec<VoxelLight>);
+
+impl Default for ChunkLightData {
+    fn default() -> Self {
+        Self(vec![VoxelLight::default(); ChunkBlockShape::USIZE])
+    }
+}
+
+impl ChunkLightData {
+    pub fn get(&self, position: UVec3) -> VoxelLight {
+        self.0[ChunkBlockShape::linearize(position.to_array()) as usize]
+    }
+
+    pub fn set(&mut self, position: UVec3, light: VoxelLight) {
+        self.0[ChunkBlockShape::linearize(position.to_array()) as usize] = light;
+    }
+}
+
+/// Light of a chunk padded with the touching layer of each neighbour, laid out like `ChunkVoxelData`
+#[derive(Clone, Debug)]
+pub struct ChunkLightVolume(Vec<VoxelLight>);
+
+/// Everything is lit by the open sky, as if no neighbours were loaded
+impl Default for ChunkLightVolume {
+    fn default() -> Self {
+        Self(vec![VoxelLight::SKY; ChunkVoxelShape::USIZE])
+    }
+}
+
+impl ChunkLightVolume {
+    /// Neighbours are indexed by `BlockSide`, the padding towards missing ones is lit by the open sky
+    pub fn from_light_data_with_neighbours(light_data: &ChunkLightData, neighbours: [Option<&ChunkLightData>; 6]) -> Self {
+        const FAR: u32 = CHUNK_SIZE_OUTER - 1;
+        const LAST: u32 = CHUNK_SIZE - 1;
+
+        let mut volume = Self::default();
+
+        for i in 0..ChunkBlockShape::SIZE {
+            let [x, y, z] = ChunkBlockShape::delinearize(i);
+            volume.0[ChunkVoxelShape::linearize([x + 1, y + 1, z + 1]) as usize] = light_data.0[i as usize];
+        }
+
+        for side in BlockSide::iter() {
+            let Some(neighbour) = neighbours[side as usize] else {
+                continue;
+            };
+
+            for a in 0..CHUNK_SIZE {
+                for b in 0..CHUNK_SIZE {
+                    let (padding_position, neighbour_position) = match side {
+                        BlockSide::Front => ([a + 1, b + 1, FAR], [a, b, 0]),
+                        BlockSide::Back => ([a + 1, b + 1, 0], [a, b, LAST]),
+                        BlockSide::Left => ([0, a + 1, b + 1], [LAST, a, b]),
+                        BlockSide::Right => ([FAR, a + 1, b + 1], [0, a, b]),
+                        BlockSide::Top => ([a + 1, FAR, b + 1], [a, 0, b]),
+                        BlockSide::Bottom => ([a + 1, 0, b + 1], [a, LAST, b]),
+                    };
+
+                    volume.0[ChunkVoxelShape::linearize(padding_position) as usize] = neighbour.get(UVec3::from_array(neighbour_position));
+                }
+            }
+        }
+
+        volume
+    }
+
+    /// Light at a padded voxel position
+    pub fn get(&self, position: UVec3) -> VoxelLight {
+        self.0[ChunkVoxelShape::linearize(position.to_array()) as usize]
+    }
+}
+
+/// Fills in `VoxelFace::light` of the voxels inside the padding, `faces` holding a buffer per `BlockSide`:
+/// each face is lit by the voxel it looks into
+pub fn compute_face_light(voxels: &[BlockVoxel], faces: &mut [Vec<VoxelFace>; 6], light: &ChunkLightVolume) {
+    for x in 1..=CHUNK_SIZE {
+        for y in 1..=CHUNK_SIZE {
+            for z in 1..=CHUNK_SIZE {
+                let index = ChunkVoxelShape::linearize([x, y, z]) as usize;
+
+                if voxels[index].get_visibility() == VoxelVisibility::Empty {
+                    continue;
+                }
+
+                for side in BlockSide::iter() {
+                    let facing_position = (IVec3::new(x as i32, y as i32, z as i32) + side.normal()).as_uvec3();
+                    faces[side as usize][index].light = light.get(facing_position).to_bits();
+                }
+            }
+        }
+    }
+}
+
+fn is_transparent(block: Option<&Arc<BlockInfo>>) -> bool {
+    block.is_none_or(|block_info| block_info.is_translucent || !block_info.model.is_cube())
+}
+
+fn emission(block: Option<&Arc<BlockInfo>>, channel: LightChannel) -> u8 {
+    let Some(block_info) = block else {
+        return 0;
+    };
+
+    match channel {
+        LightChannel::Sky => 0,
+        LightChannel::Red => block_info.get_emitted_light()[0],
+        LightChannel::Green => block_info.get_emitted_light()[1],
+        LightChannel::Blue => block_info.get_emitted_light()[2],
+    }
+}
+
+/// Blocks & light of the loaded chunks a light update may reach. Light fades by one level per block, so
+/// it never gets further than the columns of chunks right next to the one it starts in.
+pub struct LightWorld<'a> {
+    block_info_registry: &'a BlockInfoRegistry,
+    chunks: HashMap<IVec3, (&'a ChunkBlockData, &'a mut ChunkLightData)>,
+    changed_chunks: HashSet<IVec3>,
+}
+
+impl<'a> LightWorld<'a> {
+    pub fn new(block_info_registry: &'a BlockInfoRegistry) -> Self {
+        Self {
+            block_info_registry,
+            chunks: HashMap::default(),
+            changed_chunks: HashSet::default(),
+        }
+    }
+
+    pub fn insert(&mut self, chunk_position: IVec3, block_data: &'a ChunkBlockData, light_data: &'a mut ChunkLightData) {
+        self.chunks.insert(chunk_position, (block_data, light_data));
+    }
+
+    /// Chunks whose light changed so far
+    pub fn changed_chunks(&self) -> &HashSet<IVec3> {
+        &self.changed_chunks
+    }
+
+    /// Block at a world-space position, `None` if its chunk isn't loaded
+    fn block(&self, block_position: IVec3) -> Option<Option<&Arc<BlockInfo>>> {
+        let (chunk_position, local_position) = split_block_position(block_position);
+        let (block_data, _) = self.chunks.get(&chunk_position)?;
+
+        Some(self.block_info_registry.resolve_block_id(block_data.get(local_position)))
+    }
+
+    /// Light at a world-space position, `None` if its chunk isn't loaded
+    pub fn light(&self, block_position: IVec3) -> Option<VoxelLight> {
+        let (chunk_position, local_position) = split_block_position(block_position);
+        let (_, light_data) = self.chunks.get(&chunk_position)?;
+
+        Some(light_data.get(local_position))
+    }
+
+    fn set_light(&mut self, block_position: IVec3, channel: LightChannel, level: u8) {
+        let (chunk_position, local_position) = split_block_position(block_position);
+
+        if let Some((_, light_data)) = self.chunks.get_mut(&chunk_position) {
+            let light = light_data.get(local_position);
+
+            if light.get(channel) != level {
+                light_data.set(local_position, light.with(channel, level));
+                self.changed_chunks.insert(chunk_position);
+            }
+        }
+    }
+
+    /// Lights a chunk that just got loaded, taking in the light of its loaded neighbours & spreading its own into them
+    pub fn light_new_chunk(&mut self, chunk_position: IVec3) {
+        let chunk_origin = chunk_position * CHUNK_SIZE as i32;
+
+        for channel in LightChannel::iter() {
+            let mut queue = VecDeque::new();
+
+            for i in 0..ChunkBlockShape::SIZE {
+                let block_position = chunk_origin + UVec3::from_array(ChunkBlockShape::delinearize(i)).as_ivec3();
+
+                if self.seed(channel, block_position) {
+                    queue.push_back(block_position);
+                }
+            }
+
+            for side in BlockSide::iter() {
+                for a in 0..CHUNK_SIZE as i32 {
+                    for b in 0..CHUNK_SIZE as i32 {
+                        let on_face = match side {
+                            BlockSide::Front | BlockSide::Back => IVec3::new(a, b, 0),
+                            BlockSide::Left | BlockSide::Right => IVec3::new(0, a, b),
+                            BlockSide::Top | BlockSide::Bottom => IVec3::new(a, 0, b),
+                        };
+                        let outside = side.normal().max(IVec3::ZERO) * CHUNK_SIZE as i32 + side.normal().min(IVec3::ZERO);
+                        let neighbour_position = chunk_origin + on_face + outside;
+
+                        if self.light(neighbour_position).is_some_and(|light| light.get(channel) > 0) {
+                            queue.push_back(neighbour_position);
+                        }
+                    }
+                }
+            }
+
+            self.propagate(channel, queue);
+        }
+
+        // The chunk below got lit as if it had the open sky above it
+        let mut queue = VecDeque::new();
+
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let bottom_position = chunk_origin + IVec3::new(x, 0, z);
+                let below_position = bottom_position - IVec3::Y;
+
+                let is_sky_blocked = self.light(bottom_position).is_some_and(|light| light.get(LightChannel::Sky) < MAX_LIGHT_LEVEL);
+                let is_below_sunlit = self.light(below_position).is_some_and(|light| light.get(LightChannel::Sky) == MAX_LIGHT_LEVEL);
+
+                if is_sky_blocked && is_below_sunlit {
+                    self.remove_light(LightChannel::Sky, below_position, &mut queue);
+                }
+            }
+        }
+
+        self.propagate(LightChannel::Sky, queue);
+    }
+
+    /// Updates the light around a block that just got placed or removed
+    pub fn update_block(&mut self, block_position: IVec3) {
+        if self.light(block_position).is_none() {
+            return;
+        }
+
+        for channel in LightChannel::iter() {
+            let mut queue = VecDeque::new();
+
+            self.remove_light(channel, block_position, &mut queue);
+
+            // Whatever light is left around the block flows back in, if the block lets it
+            for side in BlockSide::iter() {
+                let neighbour_position = block_position + side.normal();
+
+                if self.light(neighbour_position).is_some_and(|light| light.get(channel) > 0) {
+                    queue.push_back(neighbour_position);
+                }
+            }
+
+            if self.seed(channel, block_position) {
+                queue.push_back(block_position);
+            }
+
+            self.propagate(channel, queue);
+        }
+    }
+
+    /// Sets the light a voxel has on its own: open sky at the top of the loaded world, or the light its block
+    /// gives off. Returns whether the voxel has any.
+    fn seed(&mut self, channel: LightChannel, block_position: IVec3) -> bool {
+        let Some(block) = self.block(block_position) else {
+            return false;
+        };
+
+        // Nothing in unloaded space blocks the sky, including the space above the world's highest layer
+        let level = match channel {
+            LightChannel::Sky => match is_transparent(block) && self.light(block_position + IVec3::Y).is_none() {
+                true => MAX_LIGHT_LEVEL,
+                false => 0,
+            },
+            LightChannel::Red | LightChannel::Green | LightChannel::Blue => emission(block, channel),
+        };
+
+        if level == 0 || self.light(block_position).is_some_and(|light| light.get(channel) >= level) {
+            return false;
+        }
+
+        self.set_light(block_position, channel, level);
+
+        true
+    }
+
+    /// Darkens everything lit through `block_position`. Voxels lit from elsewhere end up in `queue`, so
+    /// propagating it fills the darkened area back in.
+    fn remove_light(&mut self, channel: LightChannel, block_position: IVec3, queue: &mut VecDeque<IVec3>) {
+        let Some(level) = self.light(block_position).map(|light| light.get(channel)) else {
+            return;
+        };
+
+        let mut removal_queue = VecDeque::from([(block_position, level)]);
+        self.set_light(block_position, channel, 0);
+
+        while let Some((position, level)) = removal_queue.pop_front() {
+            for side in BlockSide::iter() {
+                let neighbour_position = position + side.normal();
+                let Some(neighbour_level) = self.light(neighbour_position).map(|light| light.get(channel)) else {
+                    continue;
+                };
+
+                if neighbour_level == 0 {
+                    continue;
+                }
+
+                let is_sunlight_below = channel == LightChannel::Sky && side == BlockSide::Bottom && level == MAX_LIGHT_LEVEL;
+
+                if neighbour_level < level || is_sunlight_below {
+                    self.set_light(neighbour_position, channel, 0);
+                    removal_queue.push_back((neighbour_position, neighbour_level));
+
+                    // Light sources lose their light along with everything else, but get it right back
+                    if self.seed(channel, neighbour_position) {
+                        queue.push_back(neighbour_position);
+                    }
+                } else {
+                    queue.push_back(neighbour_position);
+                }
+            }
+        }
+    }
+
+    /// Spreads the light of the queued voxels to their neighbours, one level less for every block it travels,
+    /// except for sunlight going straight down
+    fn propagate(&mut self, channel: LightChannel, mut queue: VecDeque<IVec3>) {
+        while let Some(position) = queue.pop_front() {
+            let Some(level) = self.light(position).map(|light| light.get(channel)) else {
+                continue;
+            };
+
+            for side in BlockSide::iter() {
+                let neighbour_position = position + side.normal();
+                let new_level = match channel == LightChannel::Sky && side == BlockSide::Bottom && level == MAX_LIGHT_LEVEL {
+                    true => MAX_LIGHT_LEVEL,
+                    false => level.saturating_sub(1),
+                };
+
+                let Some(neighbour_light) = self.light(neighbour_position) else {
+                    continue;
+                };
+
+                if neighbour_light.get(channel) >= new_level || !self.block(neighbour_position).is_some_and(is_transparent) {
+                    continue;
+                }
+
+                self.set_light(neighbour_position, channel, new_level);
+                queue.push_back(neighbour_position);
+            }
+        }
+    }
+}
+
+/// Lights chunks as they finish loading & relights around every block update, then re-meshes chunks whose
+/// light changed, along with their neighbours, whose faces show the light at the chunk's border
+pub fn update_light(
+    mut commands: Commands,
+    mut block_updates: EventReader<BlockUpdate>,
+    mut chunks: Query<(&ChunkPosition, Ref<ChunkBlockData>, &mut ChunkLightData)>,
+    chunk_map: Res<ChunkMap>,
+    block_info_registry: Res<BlockInfoRegistry>,
+    loaded_chunks: Query<(), With<ChunkBlockData>>,
+) {
+    // Block & light data get spawned together, so new block data means the chunk hasn't been lit yet
+    let new_chunks = chunks
+        .iter()
+        .filter(|(_, block_data, _)| block_data.is_added())
+        .map(|(chunk_position, _, _)| chunk_position.0)
+        .collect::<Vec<_>>();
+    let block_positions = block_updates.read().map(|event| event.block_position()).collect::<Vec<_>>();
+
+    if new_chunks.is_empty() && block_positions.is_empty() {
+        return;
+    }
+
+    let affected_columns = new_chunks
+        .iter()
+        .copied()
+        .chain(block_positions.iter().map(|block_position| split_block_position(*block_position).0))
+        .flat_map(|chunk_position| (-1..=1).flat_map(move |x| (-1..=1).map(move |z| chunk_position.xz() + IVec2::new(x, z))))
+        .collect::<HashSet<_>>();
+
+    let mut affected_chunks = chunks
+        .iter_mut()
+        .filter(|(chunk_position, _, _)| affected_columns.contains(&chunk_position.0.xz()))
+        .map(|(chunk_position, block_data, light_data)| (chunk_position.0, (block_data.into_inner(), light_data)))
+        .collect::<HashMap<_, _>>();
+
+    // Only chunks whose light actually changed count as changed, not every chunk of the affected columns
+    let changed_chunks = {
+        let mut light_world = LightWorld::new(&block_info_registry);
+
+        for (chunk_position, (block_data, light_data)) in affected_chunks.iter_mut() {
+            light_world.insert(*chunk_position, block_data, light_data.bypass_change_detection());
+        }
+
+        for chunk_position in new_chunks {
+            light_world.light_new_chunk(chunk_position);
+        }
+
+        for block_position in block_positions {
+            light_world.update_block(block_position);
+        }
+
+        light_world.changed_chunks().clone()
+    };
+
+    for chunk_position in changed_chunks.iter() {
+        if let Some((_, light_data)) = affected_chunks.get_mut(chunk_position) {
+            light_data.set_changed();
+        }
+    }
+
+    let dirty_chunks = changed_chunks
+        .iter()
+        .flat_map(|chunk_position| BlockSide::iter().map(|side| *chunk_position + side.normal()).chain([*chunk_position]))
+        .collect::<HashSet<_>>();
+
+    for chunk_position in dirty_chunks {
+        if let Some(entity) = chunk_map.get(chunk_position).filter(|entity| loaded_chunks.contains(*entity)) {
+            commands.entity(entity).insert(ChunkMeshDirty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_definition::BlockDefinitions;
+    use crate::block_info::{BlockId, AIR_BLOCK_ID};
+    use crate::world::chunk::Chunk;
+
+    struct TestWorld {
+        block_info_registry: BlockInfoRegistry,
+        block_data: HashMap<IVec3, ChunkBlockData>,
+        light_data: HashMap<IVec3, ChunkLightData>,
+    }
+
+    impl TestWorld {
+        fn with_chunks(block_info_registry: &BlockInfoRegistry, chunk_positions: &[IVec3]) -> Self {
+            let mut world = Self {
+                block_info_registry: block_info_registry.clone(),
+                block_data: HashMap::default(),
+                light_data: HashMap::default(),
+            };
+
+            for chunk_position in chunk_positions {
+                world.block_data.insert(*chunk_position, ChunkBlockData::default());
+                world.light_data.insert(*chunk_position, ChunkLightData::default());
+            }
+
+            world
+        }
+
+        fn set_block(&mut self, block_position: IVec3, block_id: BlockId) {
+            let (chunk_position, local_position) = split_block_position(block_position);
+            self.block_data.get_mut(&chunk_position).expect("chunk is loaded").set(local_position, block_id);
+        }
+
+        fn light_world(&mut self) -> LightWorld<'_> {
+            let mut light_world = LightWorld::new(&self.block_info_registry);
+
+            for (chunk_position, light_data) in self.light_data.iter_mut() {
+                light_world.insert(*chunk_position, &self.block_data[chunk_position], light_data);
+            }
+
+            light_world
+        }
+
+        fn light(&self, channel: LightChannel, block_position: IVec3) -> u8 {
+            let (chunk_position, local_position) = split_block_position(block_position);
+            self.light_data[&chunk_position].get(local_position).get(channel)
+        }
+    }
+
+    #[test]
+    fn sunlight_fades_under_overhangs() {
+        let registry = BlockInfoRegistry::for_tests();
+        let mut world = TestWorld::with_chunks(&registry, &[IVec3::ZERO]);
+
+        // Roof over the half of the chunk with x < 8
+        for x in 0..8 {
+            for z in 0..CHUNK_SIZE as i32 {
+                world.set_block(IVec3::new(x, 10, z), registry.get_block_info("potato_crust:dirt").id);
+            }
+        }
+
+        world.light_world().light_new_chunk(IVec3::ZERO);
+
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(12, 0, 8)), MAX_LIGHT_LEVEL);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(7, 11, 8)), MAX_LIGHT_LEVEL);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(7, 10, 8)), 0);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(7, 5, 8)), MAX_LIGHT_LEVEL - 1);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(2, 5, 8)), MAX_LIGHT_LEVEL - 6);
+    }
+
+    #[test]
+    fn block_light_crosses_chunk_borders() {
+        let registry = BlockInfoRegistry::for_tests();
+        let torch = registry.get_block_info("potato_crust:torch");
+        let mut world = TestWorld::with_chunks(&registry, &[IVec3::ZERO]);
+
+        world.set_block(IVec3::new(15, 8, 8), torch.id);
+        world.light_world().light_new_chunk(IVec3::ZERO);
+
+        // Neighbour loading later takes in the light spilling over its border
+        world.block_data.insert(IVec3::X, ChunkBlockData::default());
+        world.light_data.insert(IVec3::X, ChunkLightData::default());
+
+        {
+            let mut light_world = world.light_world();
+            light_world.light_new_chunk(IVec3::X);
+            assert!(light_world.changed_chunks().contains(&IVec3::X));
+        }
+
+        assert_eq!(world.light(LightChannel::Red, IVec3::new(15, 8, 8)), torch.get_emitted_light()[0]);
+        assert_eq!(world.light(LightChannel::Red, IVec3::new(17, 8, 8)), torch.get_emitted_light()[0] - 2);
+        assert_eq!(world.light(LightChannel::Red, IVec3::new(20, 10, 8)), torch.get_emitted_light()[0] - 7);
+    }
+
+    #[test]
+    fn block_updates_relight_their_surroundings() {
+        let registry = BlockInfoRegistry::for_tests();
+        let torch = registry.get_block_info("potato_crust:torch");
+        let mut world = TestWorld::with_chunks(&registry, &[IVec3::ZERO]);
+        world.light_world().light_new_chunk(IVec3::ZERO);
+
+        let update_block = |world: &mut TestWorld, block_position: IVec3, block_id: BlockId| {
+            world.set_block(block_position, block_id);
+            world.light_world().update_block(block_position);
+        };
+
+        // Block over a sunlit column shades everything below it
+        update_block(&mut world, IVec3::new(5, 10, 5), registry.get_block_info("potato_crust:dirt").id);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(5, 10, 5)), 0);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(5, 9, 5)), MAX_LIGHT_LEVEL - 1);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(5, 0, 5)), MAX_LIGHT_LEVEL - 1);
+
+        update_block(&mut world, IVec3::new(5, 10, 5), AIR_BLOCK_ID);
+        assert_eq!(world.light(LightChannel::Sky, IVec3::new(5, 0, 5)), MAX_LIGHT_LEVEL);
+
+        update_block(&mut world, IVec3::new(3, 3, 3), torch.id);
+        assert_eq!(world.light(LightChannel::Red, IVec3::new(3, 3, 6)), torch.get_emitted_light()[0] - 3);
+
+        update_block(&mut world, IVec3::new(3, 3, 3), AIR_BLOCK_ID);
+        assert!((0..ChunkBlockShape::SIZE).all(|i| world.light_data[&IVec3::ZERO].0[i as usize].get(LightChannel::Red) == 0));
+    }
+
+    fn light_at(app: &mut App, chunk: Entity, position: UVec3) -> [u8; 3] {
+        let light = app.world().get::<ChunkLightData>(chunk).expect("chunk has light data").get(position);
+
+        [LightChannel::Red, LightChannel::Green, LightChannel::Blue].map(|channel| light.get(channel))
+    }
+
+    #[test]
+    fn emitters_seed_and_unseed_light_in_a_headless_world() {
+        let registry = BlockInfoRegistry::for_tests();
+        let lava = registry.get_block_info("potato_crust:lava");
+        assert_eq!(lava.get_emitted_light(), [15, 9, 5]);
+
+        let mut app = App::new();
+        app.add_event::<BlockUpdate>()
+            .init_resource::<ChunkMap>()
+            .insert_resource(registry.clone())
+            .add_systems(Update, update_light);
+
+        let chunk = app.world_mut().spawn(Chunk::new(IVec3::ZERO)).id();
+        app.world_mut().resource_mut::<ChunkMap>().insert(IVec3::ZERO, chunk);
+        app.update();
+
+        let emitter_position = UVec3::new(8, 8, 8);
+        app.world_mut().get_mut::<ChunkBlockData>(chunk).expect("chunk has block data").set(emitter_position, lava.id);
+        app.world_mut().send_event(BlockUpdate::new(emitter_position.as_ivec3()));
+        app.world_mut().entity_mut(chunk).remove::<ChunkMeshDirty>();
+        app.update();
+
+        // Each channel fades by one level per block travelled, counting steps along every axis
+        assert_eq!(light_at(&mut app, chunk, emitter_position), [15, 9, 5]);
+        assert_eq!(light_at(&mut app, chunk, UVec3::new(8, 8, 11)), [12, 6, 2]);
+        assert_eq!(light_at(&mut app, chunk, UVec3::new(12, 10, 8)), [9, 3, 0]);
+        assert_eq!(light_at(&mut app, chunk, UVec3::new(0, 8, 8)), [7, 1, 0]);
+        assert_eq!(light_at(&mut app, chunk, UVec3::new(0, 0, 0)), [0, 0, 0]);
+        assert!(app.world().get::<ChunkMeshDirty>(chunk).is_some(), "relit chunk gets re-meshed");
+
+        app.world_mut().get_mut::<ChunkBlockData>(chunk).expect("chunk has block data").set(emitter_position, AIR_BLOCK_ID);
+        app.world_mut().send_event(BlockUpdate::new(emitter_position.as_ivec3()));
+        app.update();
+
+        assert_eq!(light_at(&mut app, chunk, emitter_position), [0, 0, 0]);
+        assert_eq!(light_at(&mut app, chunk, UVec3::new(8, 8, 11)), [0, 0, 0]);
+    }
+
+    #[test]
+    fn emission_levels_above_the_maximum_are_rejected() {
+        let registry = BlockInfoRegistry::for_tests();
+        let torch = registry.get_block_info("potato_crust:torch");
+        assert_eq!(torch.get_emitted_light()[0], torch.light_emission);
+
+        for (block, expected) in [
+            ("light_emission: 16", "above the maximum of 15"),
+            ("light_emission: 8, light_tint: (1.0, 2.0, 0.0)", "outside of 0.0 to 1.0"),
+        ] {
+            let definitions = BlockDefinitions::from_ron(
+                format!(r#"#![enable(implicit_some)] (namespace: "test", blocks: [(name: "lamp", {block})])"#).as_bytes(),
+            )
+            .expect("parse block definitions");
+
+            let error = BlockInfoRegistry::default()
+                .register_definitions(&definitions, |_| Some(0))
+                .expect_err("invalid emission accepted");
+            assert!(error.to_string().contains(expected), "{error}");
+        }
+    }
+}
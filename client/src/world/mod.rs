@@ -1,19 +1,44 @@
 use bevy::prelude::*;
 use crate::assets::AppState;
-use crate::world::systems::{ChunkDespawn, ChunkSpawn, handle_despawn_chunk_events, handle_spawn_chunk_events, on_world_update};
+use crate::world::chunk_map::ChunkMap;
+use crate::world::explosion::{detonate_primed_explosives, PrimedExplosives};
+use crate::world::generator::WorldGeneratorSettings;
+use crate::world::interaction::{BlockInteractionSettings, handle_block_interaction};
+use crate::world::lifecycle::ChunkLifecycles;
+use crate::world::light::update_light;
+use crate::world::region::RegionStorage;
+use crate::world::systems::{BlockUpdate, ChunkDespawn, ChunkLoadingConfig, ChunkSpawn, ChunkTaskBudget, finish_chunk_generation_tasks, finish_chunk_mesh_tasks, handle_despawn_chunk_events, handle_spawn_chunk_events, mark_changed_chunks_dirty, mark_modified_chunks, on_world_update, queue_chunk_lifecycle_requests, remesh_dirty_chunks};
 
 pub mod chunk;
+pub mod chunk_map;
+pub mod collision;
+pub mod explosion;
+pub mod generator;
+pub mod interaction;
+pub mod lifecycle;
+pub mod light;
+pub mod occlusion;
+pub mod palette;
+pub mod raycast;
+pub mod region;
+pub mod systems;
 pub mod voxel;
-mod block;
-mod systems;
-
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ChunkSpawn>()
+        app.init_resource::<WorldGeneratorSettings>()
+            .init_resource::<ChunkMap>()
+            .init_resource::<ChunkLifecycles>()
+            .init_resource::<BlockInteractionSettings>()
+            .init_resource::<RegionStorage>()
+            .init_resource::<ChunkTaskBudget>()
+            .init_resource::<ChunkLoadingConfig>()
+            .init_resource::<PrimedExplosives>()
+            .add_event::<ChunkSpawn>()
             .add_event::<ChunkDespawn>()
-            .add_systems(Update, (on_world_update, handle_despawn_chunk_events, handle_spawn_chunk_events).chain().run_if(in_state(AppState::InGame)));
+            .add_event::<BlockUpdate>()
+            .add_systems(Update, (on_world_update, queue_chunk_lifecycle_requests, handle_despawn_chunk_events, handle_spawn_chunk_events, finish_chunk_generation_tasks, handle_block_interaction, detonate_primed_explosives, mark_modified_chunks, mark_changed_chunks_dirty, update_light, remesh_dirty_chunks, finish_chunk_mesh_tasks).chain().run_if(in_state(AppState::InGame)));
     }
 }
\ No newline at end of file
@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+use block_mesh::ndshape::ConstShape;
+use block_mesh::{Voxel, VoxelVisibility};
+use strum::IntoEnumIterator;
+
+use crate::block_info::BlockSide;
+use crate::world::chunk::{ChunkVoxelShape, CHUNK_SIZE};
+use crate::world::voxel::{BlockVoxel, VoxelFace};
+
+/// Ambient occlusion of a face vertex, from 0 (open) to 3 (tucked into a corner), judging by the two voxels
+/// beside the vertex & the one diagonal to it, all in the layer of voxels the face looks into.
+///
+/// `vertex` is a corner of a face in padded voxel coordinates, `face_center` tells which way the face extends from it.
+/// Only the padding's faces get filled in from neighbouring chunks, so corners along chunk edges may come out
+/// a bit lighter than they should.
+pub fn vertex_occlusion(voxels: &[BlockVoxel], vertex: Vec3, normal: Vec3, face_center: Vec3) -> u8 {
+    let vertex = vertex.round().as_ivec3();
+    let normal_axis = axis_of(normal.as_ivec3());
+    let [tangent_a, tangent_b] = [(normal_axis + 1) % 3, (normal_axis + 2) % 3];
+
+    // Cell coordinate along a tangent, either the one covered by the face or the one just outside of it
+    let tangent_cell = |axis: usize, is_outside: bool| {
+        let is_face_ahead = face_center[axis] > vertex[axis] as f32;
+
+        match is_face_ahead != is_outside {
+            true => vertex[axis],
+            false => vertex[axis] - 1,
+        }
+    };
+    let is_occluding = |outside_a: bool, outside_b: bool| {
+        let mut cell = IVec3::ZERO;
+        cell[normal_axis] = if normal[normal_axis] > 0.0 { vertex[normal_axis] } else { vertex[normal_axis] - 1 };
+        cell[tangent_a] = tangent_cell(tangent_a, outside_a);
+        cell[tangent_b] = tangent_cell(tangent_b, outside_b);
+
+        let voxel = voxels[ChunkVoxelShape::linearize(cell.as_uvec3().to_array()) as usize];
+        voxel.get_visibility() == VoxelVisibility::Opaque
+    };
+
+    let (side_a, side_b, corner) = (is_occluding(true, false), is_occluding(false, true), is_occluding(true, true));
+
+    match side_a && side_b {
+        true => 3,
+        false => side_a as u8 + side_b as u8 + corner as u8,
+    }
+}
+
+fn axis_of(normal: IVec3) -> usize {
+    (0..3).find(|axis| normal[*axis] != 0).expect("normal is a unit vector along an axis")
+}
+
+/// Fills in `VoxelFace::occlusion` of the voxels inside the padding, `faces` holding a buffer per `BlockSide`,
+/// so `greedy_quads` only merges faces whose corners are occluded the same way
+pub fn compute_face_occlusion(voxels: &[BlockVoxel], faces: &mut [Vec<VoxelFace>; 6]) {
+    for x in 1..=CHUNK_SIZE {
+        for y in 1..=CHUNK_SIZE {
+            for z in 1..=CHUNK_SIZE {
+                let index = ChunkVoxelShape::linearize([x, y, z]) as usize;
+
+                if voxels[index].get_visibility() == VoxelVisibility::Empty {
+                    continue;
+                }
+
+                let block_center = UVec3::new(x, y, z).as_vec3() + Vec3::splat(0.5);
+
+                for side in BlockSide::iter() {
+                    let normal = side.normal().as_vec3();
+                    let face_center = block_center + normal * 0.5;
+                    let normal_axis = axis_of(side.normal());
+                    let [tangent_a, tangent_b] = [(normal_axis + 1) % 3, (normal_axis + 2) % 3];
+
+                    faces[side as usize][index].occlusion = [(-0.5, -0.5), (0.5, -0.5), (-0.5, 0.5), (0.5, 0.5)]
+                        .into_iter()
+                        .enumerate()
+                        .fold(0, |packed, (i, (offset_a, offset_b))| {
+                            let mut vertex = face_center;
+                            vertex[tangent_a] += offset_a;
+                            vertex[tangent_b] += offset_b;
+
+                            packed | vertex_occlusion(voxels, vertex, normal, face_center) << (i * 2)
+                        });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_info::BlockInfoRegistry;
+    use crate::world::chunk::{ChunkBlockData, ChunkVoxelData};
+
+    fn voxels_with(blocks: &[[u32; 3]]) -> Vec<BlockVoxel> {
+        let registry = BlockInfoRegistry::for_tests();
+        let mut block_data = ChunkBlockData::default();
+
+        for block in blocks {
+            block_data.set(UVec3::from_array(*block), registry.get_block_info("potato_crust:dirt").id);
+        }
+
+        ChunkVoxelData::from_block_data(&block_data, &registry).0.to_vec()
+    }
+
+    // Occlusion of the top face corner of chunk-local block (2, 2, 2) facing +X +Z
+    fn top_corner_occlusion(voxels: &[BlockVoxel]) -> u8 {
+        let face_center = Vec3::new(3.5, 4.0, 3.5);
+        vertex_occlusion(voxels, Vec3::new(4.0, 4.0, 4.0), Vec3::Y, face_center)
+    }
+
+    #[test]
+    fn corners_are_occluded_by_their_three_neighbours() {
+        assert_eq!(top_corner_occlusion(&voxels_with(&[[2, 2, 2]])), 0);
+        assert_eq!(top_corner_occlusion(&voxels_with(&[[2, 2, 2], [3, 3, 2]])), 1);
+        assert_eq!(top_corner_occlusion(&voxels_with(&[[2, 2, 2], [3, 3, 3]])), 1);
+        assert_eq!(top_corner_occlusion(&voxels_with(&[[2, 2, 2], [3, 3, 2], [3, 3, 3]])), 2);
+        // Two sides fully cover the corner, whether there's a block diagonal to it or not
+        assert_eq!(top_corner_occlusion(&voxels_with(&[[2, 2, 2], [3, 3, 2], [2, 3, 3]])), 3);
+        // Blocks behind the face don't count
+        assert_eq!(top_corner_occlusion(&voxels_with(&[[2, 2, 2], [3, 2, 2], [2, 2, 3]])), 0);
+    }
+
+    #[test]
+    fn faces_only_share_merge_values_when_occluded_alike() {
+        let voxels = voxels_with(&[[2, 2, 2], [3, 2, 2], [8, 2, 2], [9, 2, 2], [3, 3, 2]]);
+        let mut faces = std::array::from_fn(|_| vec![VoxelFace::default(); voxels.len()]);
+        compute_face_occlusion(&voxels, &mut faces);
+
+        let face_occlusion = |position: [u32; 3]| {
+            let index = ChunkVoxelShape::linearize(position.map(|c| c + 1)) as usize;
+            BlockSide::iter().map(|side| faces[side as usize][index].occlusion).collect::<Vec<_>>()
+        };
+
+        assert_eq!(face_occlusion([8, 2, 2]), face_occlusion([9, 2, 2]));
+        assert_eq!(face_occlusion([8, 2, 2])[BlockSide::Top as usize], 0);
+        assert_ne!(face_occlusion([2, 2, 2]), face_occlusion([8, 2, 2]));
+        assert_ne!(face_occlusion([2, 2, 2])[BlockSide::Top as usize], 0);
+    }
+}
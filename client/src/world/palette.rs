@@ -0,0 +1,305 @@
+use std::mem::size_of;
+
+/// Fixed-length array of values stored as indices into a palette of the distinct values it holds.
+///
+/// Indices are bit-packed into `u64` words, lowest bits first and never spanning two words, using as few
+/// bits as the palette needs. The width grows whenever the palette outgrows it. Arrays holding a single
+/// value, like all-air chunks, skip the indices altogether.
+#[derive(Clone, Debug)]
+pub struct PalettedContainer<T> {
+    len: usize,
+    storage: PalettedStorage<T>,
+}
+
+#[derive(Clone, Debug)]
+enum PalettedStorage<T> {
+    Single(T),
+    Packed {
+        palette: Vec<T>,
+        bits: u32,
+        words: Vec<u64>,
+    },
+}
+
+impl<T: Clone + PartialEq> PalettedContainer<T> {
+    /// Container of `len` copies of `value`
+    pub fn new(len: usize, value: T) -> Self {
+        Self {
+            len,
+            storage: PalettedStorage::Single(value),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> &T {
+        assert!(index < self.len, "index {} out of bounds for container of length {}", index, self.len);
+
+        match &self.storage {
+            PalettedStorage::Single(value) => value,
+            PalettedStorage::Packed { palette, bits, words } => &palette[read_index(words, *bits, index)],
+        }
+    }
+
+    pub fn set(&mut self, index: usize, value: T) {
+        assert!(index < self.len, "index {} out of bounds for container of length {}", index, self.len);
+
+        if let PalettedStorage::Single(current) = &self.storage {
+            if *current == value {
+                return;
+            }
+
+            self.storage = PalettedStorage::Packed {
+                palette: vec![current.clone()],
+                bits: 1,
+                words: vec![0; word_count(self.len, 1)],
+            };
+        }
+
+        let PalettedStorage::Packed { palette, bits, words } = &mut self.storage else {
+            unreachable!("single-value storage got converted above");
+        };
+
+        let palette_index = match palette.iter().position(|entry| *entry == value) {
+            Some(palette_index) => palette_index,
+            None => {
+                palette.push(value);
+
+                if palette.len() > 1 << *bits {
+                    let new_bits = *bits + 1;
+                    let mut new_words = vec![0; word_count(self.len, new_bits)];
+
+                    for i in 0..self.len {
+                        write_index(&mut new_words, new_bits, i, read_index(words, *bits, i));
+                    }
+
+                    *bits = new_bits;
+                    *words = new_words;
+                }
+
+                palette.len() - 1
+            }
+        };
+
+        write_index(words, *bits, index, palette_index);
+    }
+
+    /// Drops palette entries no longer in use & shrinks the indices to match, going back to a single value
+    /// if that's all that's left. Worth doing once a container is done being filled in.
+    pub fn compact(&mut self) {
+        let PalettedStorage::Packed { palette, bits, words } = &self.storage else {
+            return;
+        };
+
+        let mut new_palette: Vec<T> = Vec::new();
+        let mut remapped_indices = vec![None; palette.len()];
+        let indices = (0..self.len)
+            .map(|i| {
+                let palette_index = read_index(words, *bits, i);
+
+                *remapped_indices[palette_index].get_or_insert_with(|| {
+                    new_palette.push(palette[palette_index].clone());
+                    new_palette.len() - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if new_palette.len() == 1 {
+            self.storage = PalettedStorage::Single(new_palette.remove(0));
+            return;
+        }
+
+        let new_bits = (usize::BITS - (new_palette.len() - 1).leading_zeros()).max(1);
+        let mut new_words = vec![0; word_count(self.len, new_bits)];
+
+        for (i, palette_index) in indices.into_iter().enumerate() {
+            write_index(&mut new_words, new_bits, i, palette_index);
+        }
+
+        self.storage = PalettedStorage::Packed {
+            palette: new_palette,
+            bits: new_bits,
+            words: new_words,
+        };
+    }
+
+    /// Palette & packed indices the container is made of, as `(palette, bits per index, words)`.
+    /// Single-value containers have no indices, so they're zero bits wide.
+    pub fn raw_parts(&self) -> (&[T], u32, &[u64]) {
+        match &self.storage {
+            PalettedStorage::Single(value) => (std::slice::from_ref(value), 0, &[]),
+            PalettedStorage::Packed { palette, bits, words } => (palette, *bits, words),
+        }
+    }
+
+    /// Reassembles a container of `len` values from its `raw_parts`, `None` if they don't make up one
+    pub fn from_raw_parts(len: usize, mut palette: Vec<T>, bits: u32, words: Vec<u64>) -> Option<Self> {
+        if bits == 0 {
+            return match (palette.len(), words.is_empty()) {
+                (1, true) => Some(Self::new(len, palette.remove(0))),
+                _ => None,
+            };
+        }
+
+        if bits > 32 || palette.is_empty() || palette.len() > 1 << bits || words.len() != word_count(len, bits) {
+            return None;
+        }
+
+        if (0..len).any(|i| read_index(&words, bits, i) >= palette.len()) {
+            return None;
+        }
+
+        Some(Self {
+            len,
+            storage: PalettedStorage::Packed { palette, bits, words },
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    /// Bytes taken by the container itself, its palette & its indices. Whatever the values point to isn't counted.
+    pub fn memory_usage(&self) -> usize {
+        let heap_size = match &self.storage {
+            PalettedStorage::Single(_) => 0,
+            PalettedStorage::Packed { palette, words, .. } => {
+                palette.capacity() * size_of::<T>() + words.capacity() * size_of::<u64>()
+            }
+        };
+
+        size_of::<Self>() + heap_size
+    }
+}
+
+fn word_count(len: usize, bits: u32) -> usize {
+    len.div_ceil(64 / bits as usize)
+}
+
+fn read_index(words: &[u64], bits: u32, index: usize) -> usize {
+    let indices_per_word = 64 / bits as usize;
+    let shift = (index % indices_per_word) * bits as usize;
+
+    ((words[index / indices_per_word] >> shift) & ((1 << bits) - 1)) as usize
+}
+
+fn write_index(words: &mut [u64], bits: u32, index: usize, palette_index: usize) {
+    let indices_per_word = 64 / bits as usize;
+    let shift = (index % indices_per_word) * bits as usize;
+    let word = &mut words[index / indices_per_word];
+
+    *word = (*word & !(((1 << bits) - 1) << shift)) | (palette_index as u64) << shift;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits_per_index<T>(container: &PalettedContainer<T>) -> u32 {
+        match container.storage {
+            PalettedStorage::Single(_) => 0,
+            PalettedStorage::Packed { bits, .. } => bits,
+        }
+    }
+
+    #[test]
+    fn single_value_containers_store_no_indices() {
+        let mut container = PalettedContainer::new(4096, 0u16);
+
+        container.set(17, 0);
+
+        assert_eq!(bits_per_index(&container), 0);
+        assert_eq!(container.memory_usage(), size_of::<PalettedContainer<u16>>());
+        assert!(container.iter().all(|value| *value == 0));
+    }
+
+    #[test]
+    fn index_width_grows_with_the_palette() {
+        let mut container = PalettedContainer::new(4096, 0u16);
+
+        for (i, value) in (1..=2).enumerate() {
+            container.set(i, value);
+        }
+        assert_eq!(bits_per_index(&container), 2);
+
+        for value in 3..=16 {
+            container.set(value as usize * 100, value);
+        }
+        assert_eq!(bits_per_index(&container), 5);
+
+        // Values written before the width changes survive the repacking
+        assert_eq!(*container.get(0), 1);
+        assert_eq!(*container.get(1), 2);
+        assert_eq!(*container.get(2), 0);
+        for value in 3..=16 {
+            assert_eq!(*container.get(value as usize * 100), value);
+        }
+        assert_eq!(container.iter().filter(|value| **value != 0).count(), 16);
+    }
+
+    #[test]
+    fn overwriting_keeps_neighbouring_indices_intact() {
+        let mut container = PalettedContainer::new(100, 'a');
+
+        for i in 0..100 {
+            container.set(i, ['a', 'b', 'c'][i % 3]);
+        }
+        container.set(50, 'a');
+        container.set(51, 'c');
+
+        for i in 0..100 {
+            let expected = match i {
+                50 => 'a',
+                51 => 'c',
+                _ => ['a', 'b', 'c'][i % 3],
+            };
+
+            assert_eq!(*container.get(i), expected, "value at {i}");
+        }
+    }
+
+    #[test]
+    fn compacting_drops_overwritten_values() {
+        let mut container = PalettedContainer::new(64, 0u8);
+
+        for value in 1..=5 {
+            container.set(value as usize, value);
+        }
+        for i in 1..=5 {
+            container.set(i, if i == 3 { 7 } else { 0 });
+        }
+        container.compact();
+
+        assert_eq!(bits_per_index(&container), 1);
+        assert_eq!(*container.get(3), 7);
+        assert_eq!(container.iter().filter(|value| **value == 0).count(), 63);
+
+        container.set(3, 0);
+        container.compact();
+
+        assert_eq!(bits_per_index(&container), 0);
+        assert_eq!(container.memory_usage(), size_of::<PalettedContainer<u8>>());
+    }
+
+    #[test]
+    fn raw_parts_rebuild_the_same_container() {
+        let mut container = PalettedContainer::new(100, 'a');
+        for i in 0..100 {
+            container.set(i, ['a', 'b', 'c'][i % 3]);
+        }
+
+        let (palette, bits, words) = container.raw_parts();
+        let rebuilt = PalettedContainer::from_raw_parts(100, palette.to_vec(), bits, words.to_vec()).expect("valid raw parts");
+        assert!(rebuilt.iter().eq(container.iter()));
+
+        assert_eq!(PalettedContainer::new(100, 'a').raw_parts(), (&['a'][..], 0, &[][..]));
+        assert!(PalettedContainer::from_raw_parts(100, vec!['a'], 0, vec![0]).is_none(), "single values have no indices");
+        assert!(PalettedContainer::from_raw_parts(100, vec!['a', 'b'], 1, vec![0]).is_none(), "too few words");
+        assert!(PalettedContainer::from_raw_parts(100, vec!['a', 'b', 'c'], 1, vec![0; 2]).is_none(), "palette outgrows the index width");
+        assert!(PalettedContainer::from_raw_parts(3, vec!['a', 'b', 'c'], 2, vec![0b11 << 2]).is_none(), "index outside of the palette");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn out_of_bounds_access_panics() {
+        PalettedContainer::new(8, 0u8).get(8);
+    }
+}
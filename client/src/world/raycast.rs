@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+
+use crate::block_info::BlockSide;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelRaycastHit {
+    /// World-space position of the block that was hit
+    pub block_position: IVec3,
+    /// Face of the block the ray entered through
+    pub side: BlockSide,
+    /// Distance along the ray from its origin to the entry point
+    pub distance: f32,
+}
+
+/// Walks the voxel grid along a ray (Amanatides & Woo DDA), visiting every block the ray passes
+/// through in order, until `is_solid` returns `true` or `max_distance` is exceeded.
+pub fn raycast_voxels(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    mut is_solid: impl FnMut(IVec3) -> bool,
+) -> Option<VoxelRaycastHit> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+
+    let mut block_position = origin.floor().as_ivec3();
+    let step = IVec3::new(
+        axis_step(direction.x),
+        axis_step(direction.y),
+        axis_step(direction.z),
+    );
+
+    // Distance along the ray needed to cross one whole block on each axis
+    let t_delta = direction.recip().abs();
+    // Distance along the ray to the first block boundary on each axis
+    let mut t_max = Vec3::new(
+        first_boundary_distance(origin.x, direction.x, step.x),
+        first_boundary_distance(origin.y, direction.y, step.y),
+        first_boundary_distance(origin.z, direction.z, step.z),
+    );
+
+    // Ray starting inside a block hits it right away, facing back towards the ray
+    let mut side = BlockSide::match_normal_vector(-direction);
+    let mut distance = 0.0;
+
+    loop {
+        if is_solid(block_position) {
+            return Some(VoxelRaycastHit {
+                block_position,
+                side,
+                distance,
+            });
+        }
+
+        let axis = match (t_max.x < t_max.y, t_max.x < t_max.z, t_max.y < t_max.z) {
+            (true, true, _) => 0,
+            (false, _, true) => 1,
+            _ => 2,
+        };
+
+        distance = t_max[axis];
+        if distance > max_distance {
+            return None;
+        }
+
+        block_position[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        side = match (axis, step[axis] > 0) {
+            (0, true) => BlockSide::Left,
+            (0, false) => BlockSide::Right,
+            (1, true) => BlockSide::Bottom,
+            (1, false) => BlockSide::Top,
+            (_, true) => BlockSide::Back,
+            (_, false) => BlockSide::Front,
+        };
+    }
+}
+
+fn axis_step(direction: f32) -> i32 {
+    match direction {
+        _ if direction > 0.0 => 1,
+        _ if direction < 0.0 => -1,
+        _ => 0,
+    }
+}
+
+fn first_boundary_distance(origin: f32, direction: f32, step: i32) -> f32 {
+    match step {
+        1 => (origin.floor() + 1.0 - origin) / direction,
+        -1 => (origin - origin.floor()) / -direction,
+        _ => f32::INFINITY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hits_ground_below() {
+        let hit = raycast_voxels(Vec3::new(0.5, 5.5, 0.5), Vec3::NEG_Y, 10.0, |position| {
+            position.y <= 2
+        });
+
+        assert_eq!(
+            hit,
+            Some(VoxelRaycastHit {
+                block_position: IVec3::new(0, 2, 0),
+                side: BlockSide::Top,
+                distance: 2.5,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_entry_face_in_negative_coordinates() {
+        let hit = raycast_voxels(Vec3::new(-0.5, 0.5, -0.5), Vec3::NEG_X, 10.0, |position| {
+            position == IVec3::new(-4, 0, -1)
+        });
+
+        assert_eq!(
+            hit,
+            Some(VoxelRaycastHit {
+                block_position: IVec3::new(-4, 0, -1),
+                side: BlockSide::Right,
+                distance: 2.5,
+            })
+        );
+    }
+
+    #[test]
+    fn walks_diagonals_without_skipping_blocks() {
+        let mut visited = vec![];
+        let hit = raycast_voxels(Vec3::new(0.5, 0.5, 0.5), Vec3::new(1.0, 0.0, 1.0), 3.0, |position| {
+            visited.push(position);
+            false
+        });
+
+        assert_eq!(hit, None);
+        for pair in visited.windows(2) {
+            let step = pair[1] - pair[0];
+            assert_eq!(step.abs().element_sum(), 1, "ray skipped from {} to {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn stops_at_max_distance() {
+        let hit = raycast_voxels(Vec3::new(0.5, 0.5, 0.5), Vec3::Z, 4.0, |position| {
+            position.z == 5
+        });
+
+        assert_eq!(hit, None);
+    }
+}
@@ -0,0 +1,538 @@
+//! On-disk storage for chunk block data.
+//!
+//! Chunks are grouped into cubic regions of `REGION_SIZE`³ chunks, each region living in its own
+//! `r.<x>.<y>.<z>.pcr` file. Only chunks that were actually saved are present in a region file,
+//! everything else is expected to be regenerated from the world seed.
+//!
+//! Region file layout (all integers little-endian):
+//! - magic `PCRG`, `u8` format version
+//! - `u16` block name count, followed by `u16` name length + registry name bytes for each: the region's
+//!   `BlockIdMapping`, which only ever grows so chunks saved earlier keep their meaning. Version 2 files
+//!   store `u8` name lengths instead, which are still read.
+//! - `u16` entry count, followed by `(u16 chunk index within region, u32 payload length)` entries
+//! - chunk payloads, concatenated in entry order
+//!
+//! Chunk payload layout:
+//! - `u16` palette length, followed by `u16` palette entries: block IDs of the region's mapping, `0` for air
+//! - `u8` bits per block index, `0` when the whole chunk is a single palette entry
+//! - block indices packed into `u64` words, lowest bits first, never spanning two words
+//!
+//! which is the chunk's `PalettedContainer` as it's kept in memory, only with its palette translated to the
+//! region's mapping.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+use thiserror::Error;
+
+use crate::block_info::{BlockId, BlockIdMapping, BlockInfoRegistry, AIR_BLOCK_ID};
+use crate::world::chunk::ChunkBlockData;
+
+pub const REGION_SIZE: u32 = 8;
+pub const REGION_FILE_MAGIC: &[u8; 4] = b"PCRG";
+pub const REGION_FILE_VERSION: u8 = 3;
+/// Last version that stored block name lengths as a single byte
+const REGION_FILE_VERSION_U8_NAME_LENGTHS: u8 = 2;
+
+pub type RegionShape = ConstShape3u32<REGION_SIZE, REGION_SIZE, REGION_SIZE>;
+
+#[derive(Debug, Error)]
+pub enum RegionError {
+    #[error("region file I/O failed: {0}")]
+    Io(#[from] io::Error),
+    #[error("not a region file (bad magic bytes)")]
+    InvalidMagic,
+    #[error("unsupported region file version {0}")]
+    UnsupportedVersion(u8),
+    #[error("corrupted chunk data: {0}")]
+    Corrupted(&'static str),
+    #[error("block name `{0}` is too long to be saved")]
+    NameTooLong(String),
+}
+
+#[derive(Clone, Debug, Resource)]
+pub struct RegionStorage {
+    pub directory: PathBuf,
+}
+
+impl Default for RegionStorage {
+    fn default() -> Self {
+        Self::new("saves/world")
+    }
+}
+
+impl RegionStorage {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Reads a chunk back from its region file, `None` if it was never saved
+    pub fn load_chunk(
+        &self,
+        chunk_position: IVec3,
+        block_info_registry: &BlockInfoRegistry,
+    ) -> Result<Option<ChunkBlockData>, RegionError> {
+        let (region_position, chunk_index) = split_chunk_position(chunk_position);
+
+        let mut file = match fs::File::open(self.region_path(region_position)) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let (block_id_mapping, entries) = read_region_header(&mut file)?;
+        let mut payload_offset = file.stream_position()?;
+
+        for (index, length) in entries {
+            if index == chunk_index {
+                let mut payload = vec![0; length as usize];
+                file.seek(SeekFrom::Start(payload_offset))?;
+                file.read_exact(&mut payload)?;
+
+                return decode_chunk(&payload, &block_id_mapping.resolve(block_info_registry)).map(Some);
+            }
+
+            payload_offset += length as u64;
+        }
+
+        Ok(None)
+    }
+
+    /// Writes chunks into their region files, replacing previously saved versions of them
+    pub fn save_chunks<'a>(
+        &self,
+        chunks: impl IntoIterator<Item = (IVec3, &'a ChunkBlockData)>,
+        block_info_registry: &BlockInfoRegistry,
+    ) -> Result<(), RegionError> {
+        let mut chunks_by_region: BTreeMap<[i32; 3], Vec<(u16, &ChunkBlockData)>> = BTreeMap::new();
+
+        for (chunk_position, block_data) in chunks {
+            let (region_position, chunk_index) = split_chunk_position(chunk_position);
+
+            chunks_by_region
+                .entry(region_position.to_array())
+                .or_default()
+                .push((chunk_index, block_data));
+        }
+
+        if chunks_by_region.is_empty() {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.directory)?;
+
+        for (region_position, new_chunks) in chunks_by_region {
+            let region_path = self.region_path(IVec3::from_array(region_position));
+            let (mut block_id_mapping, mut payloads) = match fs::read(&region_path) {
+                Ok(bytes) => read_region_payloads(&bytes)?,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => (BlockIdMapping::default(), BTreeMap::new()),
+                Err(error) => return Err(error.into()),
+            };
+
+            for (chunk_index, block_data) in new_chunks {
+                payloads.insert(chunk_index, encode_chunk(block_data, block_info_registry, &mut block_id_mapping));
+            }
+
+            // Write next to the region and swap it in, so a crash mid-write can't corrupt saved chunks
+            let temporary_path = region_path.with_extension("pcr.tmp");
+            fs::write(&temporary_path, write_region(&block_id_mapping, &payloads)?)?;
+            fs::rename(&temporary_path, &region_path)?;
+        }
+
+        Ok(())
+    }
+
+    fn region_path(&self, region_position: IVec3) -> PathBuf {
+        self.directory.join(format!(
+            "r.{}.{}.{}.pcr",
+            region_position.x, region_position.y, region_position.z
+        ))
+    }
+}
+
+/// Splits a chunk position into the position of its region and the index of the chunk within it
+fn split_chunk_position(chunk_position: IVec3) -> (IVec3, u16) {
+    let region_size = IVec3::splat(REGION_SIZE as i32);
+    let local_position = chunk_position.rem_euclid(region_size).as_uvec3();
+
+    (
+        chunk_position.div_euclid(region_size),
+        RegionShape::linearize(local_position.to_array()) as u16,
+    )
+}
+
+fn read_region_header(reader: &mut impl Read) -> Result<(BlockIdMapping, Vec<(u16, u32)>), RegionError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != REGION_FILE_MAGIC {
+        return Err(RegionError::InvalidMagic);
+    }
+
+    let version = read_u8(reader)?;
+    if version != REGION_FILE_VERSION && version != REGION_FILE_VERSION_U8_NAME_LENGTHS {
+        return Err(RegionError::UnsupportedVersion(version));
+    }
+
+    let name_count = read_u16(reader)?;
+    let names = (0..name_count)
+        .map(|_| {
+            let name_length = match version {
+                REGION_FILE_VERSION_U8_NAME_LENGTHS => read_u8(reader)? as usize,
+                _ => read_u16(reader)? as usize,
+            };
+            let mut name = vec![0; name_length];
+            reader.read_exact(&mut name)?;
+
+            String::from_utf8(name).map_err(|_| RegionError::Corrupted("block name is not valid UTF-8"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let entry_count = read_u16(reader)?;
+    let entries = (0..entry_count)
+        .map(|_| Ok((read_u16(reader)?, read_u32(reader)?)))
+        .collect::<Result<Vec<_>, RegionError>>()?;
+
+    Ok((BlockIdMapping::from_names(names), entries))
+}
+
+fn read_region_payloads(bytes: &[u8]) -> Result<(BlockIdMapping, BTreeMap<u16, Vec<u8>>), RegionError> {
+    let mut reader = bytes;
+    let (block_id_mapping, entries) = read_region_header(&mut reader)?;
+    let mut payloads = BTreeMap::new();
+
+    for (index, length) in entries {
+        let mut payload = vec![0; length as usize];
+        reader.read_exact(&mut payload)?;
+        payloads.insert(index, payload);
+    }
+
+    Ok((block_id_mapping, payloads))
+}
+
+fn write_region(block_id_mapping: &BlockIdMapping, payloads: &BTreeMap<u16, Vec<u8>>) -> Result<Vec<u8>, RegionError> {
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(REGION_FILE_MAGIC);
+    bytes.push(REGION_FILE_VERSION);
+    bytes.extend_from_slice(&(block_id_mapping.names().len() as u16).to_le_bytes());
+
+    for name in block_id_mapping.names() {
+        let name_length = u16::try_from(name.len()).map_err(|_| RegionError::NameTooLong(name.clone()))?;
+        bytes.extend_from_slice(&name_length.to_le_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+    }
+
+    bytes.extend_from_slice(&(payloads.len() as u16).to_le_bytes());
+
+    for (index, payload) in payloads {
+        bytes.extend_from_slice(&index.to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    }
+
+    for payload in payloads.values() {
+        bytes.extend_from_slice(payload);
+    }
+
+    Ok(bytes)
+}
+
+/// Encodes a chunk's `PalettedContainer` as is, with its palette translated to IDs of `block_id_mapping`,
+/// mapping blocks it doesn't contain yet
+pub fn encode_chunk(block_data: &ChunkBlockData, block_info_registry: &BlockInfoRegistry, block_id_mapping: &mut BlockIdMapping) -> Vec<u8> {
+    // Blocks that were overwritten since the chunk last got compacted shouldn't end up in the mapping
+    let mut block_data = block_data.clone();
+    block_data.compact();
+
+    let (palette, bits, words) = block_data.raw_parts();
+    let mut bytes = Vec::with_capacity(2 + palette.len() * 2 + 1 + words.len() * 8);
+    bytes.extend_from_slice(&(palette.len() as u16).to_le_bytes());
+
+    for block_id in palette {
+        // Registries can't hold more blocks than there are IDs, so neither can a mapping of registered blocks
+        let mapped_id = block_info_registry
+            .block_id_state_name(*block_id)
+            .map(|state_name| block_id_mapping.get_or_insert(&state_name).expect("block ID mapping is full"))
+            .unwrap_or(AIR_BLOCK_ID);
+
+        bytes.extend_from_slice(&mapped_id.to_le_bytes());
+    }
+
+    bytes.push(bits as u8);
+
+    for word in words {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+
+    bytes
+}
+
+/// Decodes a chunk saved with `encode_chunk`, `blocks` being its region's mapping resolved by `BlockIdMapping::resolve`
+pub fn decode_chunk(
+    payload: &[u8],
+    blocks: &[BlockId],
+) -> Result<ChunkBlockData, RegionError> {
+    let mut reader = payload;
+    let palette_length = read_u16(&mut reader)? as usize;
+    let mut palette = Vec::with_capacity(palette_length);
+
+    for _ in 0..palette_length {
+        let block_id = read_u16(&mut reader)?;
+
+        let block = blocks
+            .get(block_id as usize)
+            .copied()
+            .ok_or(RegionError::Corrupted("block ID outside of the region's mapping"))?;
+
+        palette.push(block);
+    }
+
+    let bits = read_u8(&mut reader)? as u32;
+    let mut words = Vec::with_capacity(reader.len() / 8);
+
+    while !reader.is_empty() {
+        words.push(read_u64(&mut reader)?);
+    }
+
+    let mut block_data = ChunkBlockData::from_raw_parts(palette, bits, words).ok_or(RegionError::Corrupted("invalid block palette or indices"))?;
+
+    // Separately saved blocks may resolve to the same block now, e.g. when both got removed from the game
+    block_data.compact();
+    Ok(block_data)
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::block_info::{BlockInfo, MISSING_BLOCK_NAME};
+    use crate::world::generator::{HeightmapWorldGenerator, WorldGenerator, DEFAULT_WORLD_SEED};
+    use crate::world::chunk::ChunkPosition;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("potato-crust-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+
+            Self(path)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn block_names(block_data: &ChunkBlockData, registry: &BlockInfoRegistry) -> Vec<Option<String>> {
+        block_data
+            .iter()
+            .map(|block_id| registry.resolve_block_id(block_id).map(|info| info.get_state_name()))
+            .collect()
+    }
+
+    #[test]
+    fn chunks_round_trip_through_region_files() {
+        let temp_dir = TempDir::new("region-round-trip");
+        let registry = BlockInfoRegistry::for_tests();
+        let storage = RegionStorage::new(temp_dir.path());
+        let generator = HeightmapWorldGenerator::default();
+
+        // Two chunks sharing a region, one in a region on the negative side
+        let positions = [IVec3::new(0, 0, 0), IVec3::new(3, 0, 5), IVec3::new(-1, 0, -9)];
+        let chunks = positions.map(|position| generator.generate_chunk(DEFAULT_WORLD_SEED, &ChunkPosition(position), &registry));
+
+        storage
+            .save_chunks(positions.iter().copied().zip(chunks.iter()), &registry)
+            .expect("save chunks");
+
+        for (position, expected) in positions.iter().zip(chunks.iter()) {
+            let loaded = storage
+                .load_chunk(*position, &registry)
+                .expect("load chunk")
+                .expect("saved chunk should be present");
+
+            assert_eq!(block_names(&loaded, &registry), block_names(expected, &registry), "chunk {position} changed after round trip");
+        }
+
+        assert!(storage.load_chunk(IVec3::new(1, 0, 0), &registry).expect("load chunk").is_none());
+        assert!(storage.load_chunk(IVec3::new(100, 0, 0), &registry).expect("load chunk").is_none());
+    }
+
+    #[test]
+    fn saving_replaces_only_the_given_chunk() {
+        let temp_dir = TempDir::new("region-overwrite");
+        let registry = BlockInfoRegistry::for_tests();
+        let storage = RegionStorage::new(temp_dir.path());
+        let dirt = registry.get_block_info("potato_crust:dirt");
+
+        let mut first = ChunkBlockData::default();
+        first.set(UVec3::new(1, 2, 3), dirt.id);
+        let second = ChunkBlockData::default();
+
+        storage.save_chunks([(IVec3::X, &first), (IVec3::Z, &first)], &registry).expect("save chunks");
+        storage.save_chunks([(IVec3::X, &second)], &registry).expect("save chunk");
+
+        let loaded_first = storage.load_chunk(IVec3::X, &registry).expect("load chunk").expect("chunk present");
+        let loaded_second = storage.load_chunk(IVec3::Z, &registry).expect("load chunk").expect("chunk present");
+
+        assert_eq!(block_names(&loaded_first, &registry), block_names(&second, &registry));
+        assert_eq!(block_names(&loaded_second, &registry), block_names(&first, &registry));
+    }
+
+    #[test]
+    fn palette_compresses_uniform_chunks() {
+        let registry = BlockInfoRegistry::for_tests();
+        let cobblestone = registry.get_block_info("potato_crust:cobblestone");
+
+        let block_data = ChunkBlockData::filled(cobblestone.id);
+
+        let mut block_id_mapping = BlockIdMapping::default();
+        let payload = encode_chunk(&block_data, &registry, &mut block_id_mapping);
+        // palette length, a single entry, zero bits per index and no index words at all
+        assert_eq!(payload.len(), 2 + 2 + 1);
+        assert_eq!(block_id_mapping.names(), ["potato_crust:cobblestone"]);
+
+        let decoded = decode_chunk(&payload, &block_id_mapping.resolve(&registry)).expect("decode chunk");
+        assert_eq!(block_names(&decoded, &registry), block_names(&block_data, &registry));
+    }
+
+    #[test]
+    fn saved_block_ids_do_not_depend_on_registration_order() {
+        let registry = BlockInfoRegistry::for_tests();
+        let dirt = registry.get_block_info("potato_crust:dirt");
+        let grass = registry.get_block_info("potato_crust:grass");
+
+        let mut block_data = ChunkBlockData::default();
+        block_data.set(UVec3::new(0, 0, 0), grass.id);
+        block_data.set(UVec3::new(0, 1, 0), dirt.id);
+
+        // Region mapping assigned before this registry existed, in a different order & with a block it lacks
+        let mut block_id_mapping = BlockIdMapping::from_names(vec!["old:marble".to_string(), "potato_crust:dirt".to_string()]);
+        let payload = encode_chunk(&block_data, &registry, &mut block_id_mapping);
+        assert_eq!(block_id_mapping.names(), ["old:marble", "potato_crust:dirt", "potato_crust:grass"]);
+
+        let decoded = decode_chunk(&payload, &block_id_mapping.resolve(&registry)).expect("decode chunk");
+        assert_eq!(block_names(&decoded, &registry), block_names(&block_data, &registry));
+
+        let mut marble_chunk = ChunkBlockData::default();
+        marble_chunk.set(UVec3::ZERO, dirt.id);
+        let mut payload = encode_chunk(&marble_chunk, &registry, &mut block_id_mapping);
+        // Point the first palette entry, the dirt block, at the unregistered block instead
+        payload[2..4].copy_from_slice(&1u16.to_le_bytes());
+
+        let decoded = decode_chunk(&payload, &block_id_mapping.resolve(&registry)).expect("decode chunk");
+        let block_info = registry.resolve_block_id(decoded.get(UVec3::ZERO));
+        assert_eq!(block_info.map(|block_info| block_info.get_registry_name()).as_deref(), Some(MISSING_BLOCK_NAME));
+    }
+
+    #[test]
+    fn unknown_blocks_survive_being_saved_without_them() {
+        let temp_dir = TempDir::new("region-unknown-blocks");
+        let storage = RegionStorage::new(temp_dir.path());
+        let mut registry_with_block = BlockInfoRegistry::for_tests();
+        registry_with_block
+            .register("test", BlockInfo { name: "gone".to_string(), ..Default::default() })
+            .expect("register block");
+        let registry = BlockInfoRegistry::for_tests();
+
+        let mut block_data = ChunkBlockData::default();
+        block_data.set(UVec3::new(1, 2, 3), registry_with_block.get_block_info("test:gone").id);
+        block_data.set(UVec3::new(4, 5, 6), registry_with_block.get_block_info("potato_crust:dirt").id);
+        storage.save_chunks([(IVec3::ZERO, &block_data)], &registry_with_block).expect("save chunk");
+
+        // Edited & saved again by a game that lacks the block
+        let mut loaded = storage.load_chunk(IVec3::ZERO, &registry).expect("load chunk").expect("chunk present");
+        let placeholder = registry.resolve_block_id(loaded.get(UVec3::new(1, 2, 3))).expect("placeholder isn't air");
+        assert_eq!(placeholder.get_registry_name(), MISSING_BLOCK_NAME);
+        loaded.set(UVec3::new(7, 8, 9), registry.get_block_info("potato_crust:glass").id);
+        storage.save_chunks([(IVec3::ZERO, &loaded)], &registry).expect("save chunk");
+
+        let reloaded = storage.load_chunk(IVec3::ZERO, &registry_with_block).expect("load chunk").expect("chunk present");
+        let name_at = |position| registry_with_block.resolve_block_id(reloaded.get(position)).map(|block_info| block_info.get_state_name());
+        assert_eq!(name_at(UVec3::new(1, 2, 3)).as_deref(), Some("test:gone"));
+        assert_eq!(name_at(UVec3::new(4, 5, 6)).as_deref(), Some("potato_crust:dirt"));
+        assert_eq!(name_at(UVec3::new(7, 8, 9)).as_deref(), Some("potato_crust:glass"));
+    }
+
+    #[test]
+    fn long_block_names_round_trip() {
+        let temp_dir = TempDir::new("region-long-names");
+        let storage = RegionStorage::new(temp_dir.path());
+        let mut registry = BlockInfoRegistry::for_tests();
+        let long_name = "long".repeat(100);
+        registry
+            .register("test", BlockInfo { name: long_name.clone(), ..Default::default() })
+            .expect("register block");
+
+        // Names mapped after the long one have to come back in line with it
+        let mut block_data = ChunkBlockData::default();
+        block_data.set(UVec3::new(0, 0, 0), registry.get_block_info(format!("test:{long_name}")).id);
+        block_data.set(UVec3::new(1, 0, 0), registry.get_block_info("potato_crust:dirt").id);
+        block_data.set(UVec3::new(2, 0, 0), registry.get_block_info("potato_crust:glass").id);
+        storage.save_chunks([(IVec3::ZERO, &block_data)], &registry).expect("save chunk");
+
+        let loaded = storage.load_chunk(IVec3::ZERO, &registry).expect("load chunk").expect("chunk present");
+        assert_eq!(block_names(&loaded, &registry), block_names(&block_data, &registry));
+    }
+
+    #[test]
+    fn reads_single_byte_name_lengths_of_version_2_files() {
+        let mut bytes = REGION_FILE_MAGIC.to_vec();
+        bytes.extend_from_slice(&[REGION_FILE_VERSION_U8_NAME_LENGTHS, 1, 0, 17]);
+        bytes.extend_from_slice(b"potato_crust:dirt");
+        bytes.extend_from_slice(&[0, 0]);
+
+        let (block_id_mapping, payloads) = read_region_payloads(&bytes).expect("read region");
+        assert_eq!(block_id_mapping.names(), ["potato_crust:dirt"]);
+        assert!(payloads.is_empty());
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_regions() {
+        let temp_dir = TempDir::new("region-invalid");
+        let registry = BlockInfoRegistry::for_tests();
+        let storage = RegionStorage::new(temp_dir.path());
+
+        fs::create_dir_all(temp_dir.path()).expect("create temp dir");
+        fs::write(storage.region_path(IVec3::ZERO), b"definitely not a region").expect("write file");
+
+        assert!(matches!(storage.load_chunk(IVec3::ZERO, &registry), Err(RegionError::InvalidMagic)));
+    }
+}
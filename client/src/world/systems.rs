@@ -1,23 +1,100 @@
 use std::ops::Div;
 
 use bevy::prelude::*;
-use bevy::utils::HashMap;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool};
+use bevy::utils::HashSet;
+use strum::IntoEnumIterator;
 
-use crate::block_info::BlockInfoRegistry;
-use crate::material::GlobalBlockAtlasMaterial;
+use crate::block_info::{BlockInfoRegistry, BlockSide};
+use crate::material::{BlockAtlasPbrBundle, GlobalBlockAtlasMaterial};
 use crate::player::Player;
-use crate::world::chunk::{Chunk, CHUNK_SIZE, ChunkBlockData, ChunkPosition};
+use crate::world::chunk_map::ChunkMap;
+use crate::world::chunk::{Chunk, CHUNK_SIZE, ChunkBlockData, ChunkCutoutMesh, ChunkGenerationTask, ChunkMeshDirty, ChunkMeshTask, ChunkModified, ChunkNeighbours, ChunkPosition, ChunkTranslucentMesh, ChunkVoxelData, split_block_position, TRANSLUCENT_MESH_OFFSET};
+use crate::world::generator::WorldGeneratorSettings;
+use crate::world::lifecycle::ChunkLifecycles;
+use crate::world::light::{ChunkLightData, ChunkLightVolume};
+use crate::world::region::RegionStorage;
 
-// TODO: figure out how to override yet-to-be-processed despawn events with newer spawn events and vice versa
-// NOTE: could add instant::Instance to these events and before despawning, check if there's newer spawn event for the same position
+// NOTE: spawn/despawn events only update the chunk lifecycle states, so newer events override older unprocessed ones
 
 // TODO: reconsider renaming "spawn/despawn" events to "load/unload" events
-// NOTE: currently we got no world persistence, so we're just creating/destroying chunks on the fly
+// NOTE: only modified chunks get persisted, untouched ones are regenerated from the world seed when loaded again
+
+/// Which chunks are kept loaded around the player: a cylinder of `horizontal_radius` chunks around the
+/// player's chunk, reaching `vertical_radius` chunks up & down, clipped to the world's chunk layers.
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct ChunkLoadingConfig {
+    pub horizontal_radius: i32,
+    pub vertical_radius: i32,
+    /// Lowest chunk layer (chunk Y) of the world
+    pub min_layer: i32,
+    /// Highest chunk layer (chunk Y) of the world
+    pub max_layer: i32,
+}
+
+impl Default for ChunkLoadingConfig {
+    fn default() -> Self {
+        Self {
+            horizontal_radius: 5,
+            vertical_radius: 2,
+            min_layer: -1,
+            max_layer: 2,
+        }
+    }
+}
+
+impl ChunkLoadingConfig {
+    pub fn is_chunk_in_radius(&self, chunk_position: IVec3, center_chunk_position: IVec3) -> bool {
+        let horizontal_distance_squared = chunk_position.xz().distance_squared(center_chunk_position.xz());
+        let vertical_distance = (chunk_position.y - center_chunk_position.y).abs();
+
+        horizontal_distance_squared <= self.horizontal_radius * self.horizontal_radius
+            && vertical_distance <= self.vertical_radius
+            && (self.min_layer..=self.max_layer).contains(&chunk_position.y)
+    }
+
+    /// Every chunk position within radius of `center_chunk_position`, nearest first
+    pub fn chunks_in_radius(&self, center_chunk_position: IVec3) -> Vec<IVec3> {
+        let radius = IVec3::new(self.horizontal_radius, self.vertical_radius, self.horizontal_radius);
+        let from = center_chunk_position - radius;
+        let to = center_chunk_position + radius;
+
+        let mut chunk_positions = vec![];
 
-pub const WORLD_CHUNKS_HEIGHT: u32 = 1;
-pub const WORLD_CHUNKS_LOAD_RADIUS: i32 = 5;
-pub const WORLD_CHUNKS_LOAD_RADIUS_VERTICAL_MULTIPLIER: i32 = 2;
-pub const WORLD_CHUNKS_LOAD_RADIUS_VERTICAL: i32 = WORLD_CHUNKS_LOAD_RADIUS * WORLD_CHUNKS_LOAD_RADIUS_VERTICAL_MULTIPLIER;
+        for y in from.y.max(self.min_layer)..=to.y.min(self.max_layer) {
+            for x in from.x..=to.x {
+                for z in from.z..=to.z {
+                    let chunk_position = IVec3::new(x, y, z);
+
+                    if self.is_chunk_in_radius(chunk_position, center_chunk_position) {
+                        chunk_positions.push(chunk_position);
+                    }
+                }
+            }
+        }
+
+        chunk_positions.sort_by_key(|chunk_position| chunk_position.distance_squared(center_chunk_position));
+
+        chunk_positions
+    }
+}
+
+/// Limits how many finished chunk tasks get applied to the world each frame, so a burst of
+/// completed tasks doesn't turn into a frame spike of entity insertions & mesh uploads
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct ChunkTaskBudget {
+    pub max_generated_chunks_per_frame: usize,
+    pub max_meshed_chunks_per_frame: usize,
+}
+
+impl Default for ChunkTaskBudget {
+    fn default() -> Self {
+        Self {
+            max_generated_chunks_per_frame: 16,
+            max_meshed_chunks_per_frame: 16,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, Event)]
 pub struct ChunkSpawn {
@@ -41,51 +118,341 @@ impl ChunkDespawn {
     }
 }
 
+/// Sent whenever a single block gets placed or removed in an already loaded chunk
+#[derive(Debug, Clone, Copy, Event)]
+pub struct BlockUpdate {
+    block_position: IVec3,
+}
+
+impl BlockUpdate {
+    pub fn new(block_position: IVec3) -> Self {
+        Self { block_position }
+    }
+
+    pub fn block_position(&self) -> IVec3 {
+        self.block_position
+    }
+}
+
+// Despawn events are applied before spawn events, so a chunk that's unloaded & loaded again within the same frame stays
+pub fn queue_chunk_lifecycle_requests(
+    mut chunk_spawn_events: ResMut<Events<ChunkSpawn>>,
+    mut chunk_despawn_events: ResMut<Events<ChunkDespawn>>,
+    mut lifecycles: ResMut<ChunkLifecycles>,
+) {
+    for event in chunk_despawn_events.drain() {
+        lifecycles.request_unload(event.chunk_position);
+    }
+
+    for event in chunk_spawn_events.drain() {
+        lifecycles.request_load(event.chunk_position);
+    }
+}
+
 pub fn handle_spawn_chunk_events(
     mut commands: Commands,
-    mut chunk_spawn_events: ResMut<Events<ChunkSpawn>>,
-    // mut chunks: Query<(&mut Chunk, &ChunkPosition)>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    atlas_material: Res<GlobalBlockAtlasMaterial>,
+    mut lifecycles: ResMut<ChunkLifecycles>,
     block_info_registry: Res<BlockInfoRegistry>,
+    world_generator_settings: Res<WorldGeneratorSettings>,
+    region_storage: Res<RegionStorage>,
+    mut chunk_map: ResMut<ChunkMap>,
 ) {
-    for event in chunk_spawn_events.drain() {
-        // info!("Spawning chunk at position {:?}", event.chunk_position);
+    let task_pool = AsyncComputeTaskPool::get();
+
+    for chunk_position in lifecycles.take_queued() {
+        // info!("Spawning chunk at position {:?}", chunk_position);
+
+        let block_info_registry = block_info_registry.clone();
+        let world_generator_settings = world_generator_settings.clone();
+        let region_storage = region_storage.clone();
+
+        let task = task_pool.spawn(async move {
+            let saved_block_data = region_storage
+                .load_chunk(chunk_position, &block_info_registry)
+                .unwrap_or_else(|error| {
+                    error!("Failed to load chunk at position {:?}, regenerating it: {}", chunk_position, error);
+                    None
+                });
+
+            saved_block_data.unwrap_or_else(|| {
+                world_generator_settings.generator.generate_chunk(
+                    world_generator_settings.seed,
+                    &ChunkPosition(chunk_position),
+                    &block_info_registry,
+                )
+            })
+        });
+
+        let entity = commands.spawn((ChunkPosition(chunk_position), ChunkGenerationTask(task))).id();
+        chunk_map.insert(chunk_position, entity);
+    }
+}
+
+pub fn finish_chunk_generation_tasks(
+    mut commands: Commands,
+    budget: Res<ChunkTaskBudget>,
+    mut tasks: Query<(Entity, &ChunkPosition, &mut ChunkGenerationTask)>,
+) {
+    let mut finished_tasks = 0;
+
+    for (entity, chunk_position, mut task) in tasks.iter_mut() {
+        if finished_tasks >= budget.max_generated_chunks_per_frame {
+            break;
+        }
+
+        let Some(block_data) = block_on(future::poll_once(&mut task.0)) else {
+            continue;
+        };
+
+        finished_tasks += 1;
 
-        let chunk = Chunk::new(event.chunk_position);
+        debug!("Chunk {} generated, block data takes {} bytes", chunk_position.0, block_data.memory_usage());
 
-        chunk.spawn(
-            &mut commands,
-            &mut meshes,
-            &atlas_material,
-            &block_info_registry,
-        );
+        commands
+            .entity(entity)
+            .remove::<ChunkGenerationTask>()
+            .insert(Chunk::from_block_data(chunk_position.0, block_data));
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn handle_despawn_chunk_events(
     mut commands: Commands,
-    mut chunk_despawn_events: ResMut<Events<ChunkDespawn>>,
+    mut lifecycles: ResMut<ChunkLifecycles>,
     mut meshes: ResMut<Assets<Mesh>>,
-    chunks: Query<(Entity, &Handle<Mesh>, &ChunkPosition), With<ChunkBlockData>>,
+    region_storage: Res<RegionStorage>,
+    block_info_registry: Res<BlockInfoRegistry>,
+    mut chunk_map: ResMut<ChunkMap>,
+    chunks: Query<(Option<&Handle<Mesh>>, Option<&ChunkCutoutMesh>, Option<&ChunkTranslucentMesh>, &ChunkPosition, Option<&ChunkBlockData>, Has<ChunkModified>)>,
 ) {
-    for event in chunk_despawn_events.drain() {
-        let Some((entity, mesh, _)) = chunks.iter().find(|(_, _, chunk_position)| event.chunk_position.eq(&chunk_position.0)) else {
-            return;
+    let mut modified_chunks = vec![];
+    let mut despawned_positions = vec![];
+
+    for chunk_position in lifecycles.take_unloading() {
+        let Some(entity) = chunk_map.remove(chunk_position) else {
+            continue;
+        };
+        let Ok((mesh, cutout_mesh, translucent_mesh, chunk_position, block_data, is_modified)) = chunks.get(entity) else {
+            continue;
         };
 
-        // info!("Despawning chunk at position {:?}", event.chunk_position);
+        // info!("Despawning chunk at position {:?}", chunk_position);
 
-        meshes.remove(mesh);
+        // Chunks still being generated have no block data yet, despawning them drops (and cancels) their task
+        if let (Some(block_data), true) = (block_data, is_modified) {
+            modified_chunks.push((chunk_position.0, block_data));
+        }
+
+        if let Some(mesh) = mesh {
+            meshes.remove(mesh);
+        }
+        if let Some(cutout_mesh) = cutout_mesh {
+            meshes.remove(&cutout_mesh.0);
+        }
+        if let Some(translucent_mesh) = translucent_mesh {
+            meshes.remove(&translucent_mesh.0);
+        }
         commands.entity(entity).despawn_recursive();
+        despawned_positions.push(chunk_position.0);
+    }
+
+    // Neighbours still have the despawned chunk's border in their padding, hiding faces that are now exposed
+    for chunk_position in despawned_positions {
+        for side in BlockSide::iter() {
+            let Some(entity) = chunk_map.get(chunk_position + side.normal()) else {
+                continue;
+            };
+
+            if chunks.get(entity).is_ok_and(|(_, _, _, _, block_data, _)| block_data.is_some()) {
+                commands.entity(entity).insert(ChunkMeshDirty);
+            }
+        }
+    }
+
+    if let Err(error) = region_storage.save_chunks(modified_chunks, &block_info_registry) {
+        error!("Failed to save modified chunks: {}", error);
     }
 }
 
-fn is_chunk_in_radius(chunk_position: IVec3, player_chunk_position: IVec3) -> bool {
-    let horizontal_distance = player_chunk_position.xz().distance_squared(chunk_position.xz());
-    let vertical_distance = player_chunk_position.xz().distance_squared(chunk_position.xz());
+// Any change to block data after the chunk got loaded means it can no longer be regenerated from the seed
+#[allow(clippy::type_complexity)]
+pub fn mark_modified_chunks(
+    mut commands: Commands,
+    changed_chunks: Query<(Entity, Ref<ChunkBlockData>), (Changed<ChunkBlockData>, Without<ChunkModified>)>,
+) {
+    for (entity, block_data) in changed_chunks.iter() {
+        if !block_data.is_added() {
+            commands.entity(entity).insert(ChunkModified);
+        }
+    }
+}
 
-    horizontal_distance <= WORLD_CHUNKS_LOAD_RADIUS || vertical_distance <= WORLD_CHUNKS_LOAD_RADIUS_VERTICAL
+// Block data is the source of truth, so any change to it requires re-meshing the chunk.
+// Neighbours keep a copy of the chunk's border in their voxels, so they get re-meshed as well when the chunk
+// gets spawned, or when a block update touches the border they share.
+pub fn mark_changed_chunks_dirty(
+    mut commands: Commands,
+    mut block_updates: EventReader<BlockUpdate>,
+    changed_chunks: Query<(&ChunkPosition, Ref<ChunkBlockData>), Changed<ChunkBlockData>>,
+    chunk_map: Res<ChunkMap>,
+    loaded_chunks: Query<(), With<ChunkBlockData>>,
+) {
+    if changed_chunks.is_empty() && block_updates.is_empty() {
+        return;
+    }
+
+    let mut affected_positions = HashSet::new();
+
+    for (chunk_position, block_data) in changed_chunks.iter() {
+        affected_positions.insert(chunk_position.0);
+
+        if block_data.is_added() {
+            affected_positions.extend(BlockSide::iter().map(|side| chunk_position.0 + side.normal()));
+        }
+    }
+
+    for event in block_updates.read() {
+        let (chunk_position, local_position) = split_block_position(event.block_position);
+
+        for side in BlockSide::iter() {
+            let neighbour_position = local_position.as_ivec3() + side.normal();
+
+            if neighbour_position.cmplt(IVec3::ZERO).any() || neighbour_position.cmpge(IVec3::splat(CHUNK_SIZE as i32)).any() {
+                affected_positions.insert(chunk_position + side.normal());
+            }
+        }
+    }
+
+    for position in affected_positions {
+        // Chunks still being generated get meshed once their block data arrives anyway
+        if let Some(entity) = chunk_map.get(position).filter(|entity| loaded_chunks.contains(*entity)) {
+            commands.entity(entity).insert(ChunkMeshDirty);
+        }
+    }
+}
+
+pub fn remesh_dirty_chunks(
+    mut commands: Commands,
+    block_info_registry: Res<BlockInfoRegistry>,
+    dirty_chunks: Query<(Entity, &ChunkPosition), With<ChunkMeshDirty>>,
+    chunk_map: Res<ChunkMap>,
+    loaded_chunks: Query<(&ChunkBlockData, &ChunkLightData)>,
+) {
+    let task_pool = AsyncComputeTaskPool::get();
+    let get_block_data = |chunk_position: IVec3| chunk_map.get(chunk_position).and_then(|entity| loaded_chunks.get(entity).ok()).map(|(block_data, _)| block_data);
+    let get_light_data = |chunk_position: IVec3| chunk_map.get(chunk_position).and_then(|entity| loaded_chunks.get(entity).ok()).map(|(_, light_data)| light_data);
+
+    for (entity, chunk_position) in dirty_chunks.iter() {
+        let Ok((block_data, light_data)) = loaded_chunks.get(entity) else {
+            continue;
+        };
+
+        // Tasks can't borrow from the world, so they get their own copy of the chunk & its neighbours
+        let block_data = block_data.clone();
+        let neighbours = ChunkNeighbours::from_lookup(chunk_position.0, get_block_data).0.map(|neighbour| neighbour.cloned());
+        let mut light_neighbours = [None; 6];
+        for side in BlockSide::iter() {
+            light_neighbours[side as usize] = get_light_data(chunk_position.0 + side.normal());
+        }
+        let light = ChunkLightVolume::from_light_data_with_neighbours(light_data, light_neighbours);
+        let block_info_registry = block_info_registry.clone();
+
+        let task = task_pool.spawn(async move {
+            let neighbours = ChunkNeighbours(neighbours.each_ref().map(Option::as_ref));
+            let voxels = ChunkVoxelData::from_block_data_with_neighbours(&block_data, &neighbours, &block_info_registry);
+
+            voxels.to_render_meshes(&light, &block_info_registry)
+        });
+
+        // Replacing a task that's still running drops, and so cancels, the outdated one
+        commands
+            .entity(entity)
+            .remove::<ChunkMeshDirty>()
+            .insert(ChunkMeshTask(task));
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn finish_chunk_mesh_tasks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    atlas_material: Res<GlobalBlockAtlasMaterial>,
+    budget: Res<ChunkTaskBudget>,
+    mut lifecycles: ResMut<ChunkLifecycles>,
+    mut tasks: Query<(Entity, &ChunkPosition, &mut ChunkMeshTask, Option<&Handle<Mesh>>, Option<&ChunkCutoutMesh>, Option<&ChunkTranslucentMesh>)>,
+) {
+    let mut finished_tasks = 0;
+
+    for (entity, chunk_position, mut task, mesh, cutout_mesh, translucent_mesh) in tasks.iter_mut() {
+        if finished_tasks >= budget.max_meshed_chunks_per_frame {
+            break;
+        }
+
+        let Some(chunk_meshes) = block_on(future::poll_once(&mut task.0)) else {
+            continue;
+        };
+
+        finished_tasks += 1;
+        lifecycles.mark_meshed(chunk_position.0);
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.remove::<ChunkMeshTask>();
+
+        match mesh {
+            Some(mesh) => {
+                meshes.insert(mesh, chunk_meshes.opaque);
+            }
+            None => {
+                entity_commands.insert(BlockAtlasPbrBundle {
+                    mesh: meshes.add(chunk_meshes.opaque),
+                    material: atlas_material.opaque.clone(),
+                    transform: Transform::from_translation(chunk_position.world_origin()),
+                    ..Default::default()
+                });
+            }
+        }
+
+        match cutout_mesh {
+            Some(cutout_mesh) => {
+                meshes.insert(&cutout_mesh.0, chunk_meshes.cutout);
+            }
+            None if chunk_meshes.cutout.count_vertices() > 0 => {
+                let cutout_mesh = meshes.add(chunk_meshes.cutout);
+
+                entity_commands
+                    .insert(ChunkCutoutMesh(cutout_mesh.clone()))
+                    .with_children(|parent| {
+                        parent.spawn(BlockAtlasPbrBundle {
+                            mesh: cutout_mesh,
+                            material: atlas_material.cutout.clone(),
+                            ..Default::default()
+                        });
+                    });
+            }
+            None => {}
+        }
+
+        match translucent_mesh {
+            Some(translucent_mesh) => {
+                meshes.insert(&translucent_mesh.0, chunk_meshes.translucent);
+            }
+            None if chunk_meshes.translucent.count_vertices() > 0 => {
+                let translucent_mesh = meshes.add(chunk_meshes.translucent);
+
+                entity_commands
+                    .insert(ChunkTranslucentMesh(translucent_mesh.clone()))
+                    .with_children(|parent| {
+                        parent.spawn(BlockAtlasPbrBundle {
+                            mesh: translucent_mesh,
+                            material: atlas_material.translucent.clone(),
+                            transform: Transform::from_translation(TRANSLUCENT_MESH_OFFSET),
+                            ..Default::default()
+                        });
+                    });
+            }
+            None => {}
+        }
+    }
 }
 
 // Queues events to load & unload chunks around player's position
@@ -93,7 +460,8 @@ pub fn on_world_update(
     mut chunk_spawn_events: ResMut<Events<ChunkSpawn>>,
     mut chunk_despawn_events: ResMut<Events<ChunkDespawn>>,
     query_player: Query<&Transform, With<Player>>,
-    query_loaded_chunks: Query<(Entity, &ChunkPosition), With<ChunkBlockData>>,
+    lifecycles: Res<ChunkLifecycles>,
+    loading_config: Res<ChunkLoadingConfig>,
 ) {
     let Ok(player_transform) = query_player.get_single() else {
         return;
@@ -101,16 +469,15 @@ pub fn on_world_update(
     let player_position = player_transform.translation;
     let player_chunk_position = player_position.div(CHUNK_SIZE as f32).floor().as_ivec3();
 
-    info!("---------------------");
-
-    let (chunks_to_keep, chunks_to_unload): (Vec<_>, Vec<_>) = query_loaded_chunks
+    let chunks_to_unload = lifecycles
         .iter()
-        .partition(|(_, chunk_position)| is_chunk_in_radius(chunk_position.0, player_chunk_position));
+        .filter(|(chunk_position, state)| state.is_loaded() && !loading_config.is_chunk_in_radius(*chunk_position, player_chunk_position))
+        .collect::<Vec<_>>();
 
     if !chunks_to_unload.is_empty() {
         let unload_events = chunks_to_unload
             .iter()
-            .map(|(_, chunk_position)| ChunkDespawn::new(chunk_position.0))
+            .map(|(chunk_position, _)| ChunkDespawn::new(*chunk_position))
             .collect::<Vec<_>>();
 
         info!("Despawning {} chunks", unload_events.len());
@@ -118,40 +485,96 @@ pub fn on_world_update(
         chunk_despawn_events.send_batch(unload_events);
     }
 
-    let kept_chunks_coord_map = HashMap::from_iter(chunks_to_keep.iter().map(|(entity, chunk_position)| (chunk_position.0, true)));
-
-    let from_y = (player_chunk_position.y - (WORLD_CHUNKS_LOAD_RADIUS_VERTICAL)).max(0);
-    let to_y = (player_chunk_position.y + (WORLD_CHUNKS_LOAD_RADIUS_VERTICAL)).min(WORLD_CHUNKS_HEIGHT as i32 - 1);
+    // Nearest chunks get requested first, so the ones around the player finish generating first
+    let load_chunk_events = loading_config
+        .chunks_in_radius(player_chunk_position)
+        .into_iter()
+        .filter(|chunk_position| !lifecycles.state(*chunk_position).is_loaded())
+        .map(ChunkSpawn::new)
+        .collect::<Vec<_>>();
 
-    let from_x = player_chunk_position.x - (WORLD_CHUNKS_LOAD_RADIUS);
-    let to_x = player_chunk_position.x + (WORLD_CHUNKS_LOAD_RADIUS);
-
-    let from_z = player_chunk_position.z - (WORLD_CHUNKS_LOAD_RADIUS);
-    let to_z = player_chunk_position.z + (WORLD_CHUNKS_LOAD_RADIUS);
+    if !load_chunk_events.is_empty() {
+        info!("Spawning {} chunks", load_chunk_events.len());
 
-    let mut load_chunk_events = vec![];
+        chunk_spawn_events.send_batch(load_chunk_events);
+    }
+}
 
-    for y in from_y..=to_y {
-        for x in from_x..=to_x {
-            for z in from_z..=to_z {
-                let chunk_position = IVec3::new(x, y, z);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_radius_is_a_clipped_cylinder() {
+        let config = ChunkLoadingConfig {
+            horizontal_radius: 3,
+            vertical_radius: 1,
+            min_layer: 0,
+            max_layer: 4,
+        };
+        let center = IVec3::new(10, 0, -10);
 
-                if !is_chunk_in_radius(chunk_position, player_chunk_position) {
-                    continue;
-                }
+        assert!(config.is_chunk_in_radius(center + IVec3::new(3, 0, 0), center));
+        assert!(config.is_chunk_in_radius(center + IVec3::new(2, 1, 2), center));
+        assert!(!config.is_chunk_in_radius(center + IVec3::new(3, 0, 1), center), "outside the horizontal circle");
+        assert!(!config.is_chunk_in_radius(center + IVec3::new(0, 2, 0), center), "above the vertical radius");
+        assert!(!config.is_chunk_in_radius(center + IVec3::new(0, -1, 0), center), "below the lowest layer");
+    }
 
-                if kept_chunks_coord_map.contains_key(&chunk_position) {
-                    continue;
-                }
+    #[test]
+    fn chunks_in_radius_are_sorted_nearest_first() {
+        let config = ChunkLoadingConfig {
+            horizontal_radius: 2,
+            vertical_radius: 2,
+            min_layer: -4,
+            max_layer: 4,
+        };
+        let center = IVec3::new(-5, 1, 3);
+        let chunk_positions = config.chunks_in_radius(center);
+
+        // 13 columns within the circle of radius 2, 5 layers each
+        assert_eq!(chunk_positions.len(), 13 * 5);
+        assert_eq!(chunk_positions[0], center);
+        assert!(chunk_positions
+            .windows(2)
+            .all(|pair| pair[0].distance_squared(center) <= pair[1].distance_squared(center)));
+    }
 
-                load_chunk_events.push(ChunkSpawn::new(chunk_position));
-            }
+    #[test]
+    fn despawning_a_chunk_re_meshes_its_loaded_neighbours() {
+        let mut app = App::new();
+        app.add_plugins(AssetPlugin::default())
+            .init_asset::<Mesh>()
+            .init_resource::<ChunkMap>()
+            .init_resource::<BlockInfoRegistry>()
+            .init_resource::<ChunkLifecycles>()
+            .insert_resource(RegionStorage::new(std::env::temp_dir().join(format!("potato-crust-despawn-{}", std::process::id()))))
+            .add_systems(Update, handle_despawn_chunk_events);
+
+        // The chunk above is still generating, so it has no padding to fix up yet
+        let positions = [IVec3::ZERO, IVec3::X, IVec3::NEG_Z, IVec3::new(2, 0, 0)];
+        for position in positions {
+            let entity = app.world_mut().spawn(Chunk::new(position)).id();
+            app.world_mut().resource_mut::<ChunkMap>().insert(position, entity);
+            app.world_mut().resource_mut::<ChunkLifecycles>().request_load(position);
         }
-    }
+        let generating = app.world_mut().spawn(ChunkPosition(IVec3::Y)).id();
+        app.world_mut().resource_mut::<ChunkMap>().insert(IVec3::Y, generating);
 
-    if !load_chunk_events.is_empty() {
-        info!("Spawning {} chunks", load_chunk_events.len());
+        let mut lifecycles = app.world_mut().resource_mut::<ChunkLifecycles>();
+        lifecycles.take_queued();
+        lifecycles.request_unload(IVec3::ZERO);
+        app.update();
 
-        chunk_spawn_events.send_batch(load_chunk_events);
+        let is_dirty = |app: &App, position: IVec3| {
+            let entity = app.world().resource::<ChunkMap>().get(position).expect("chunk is loaded");
+            app.world().get::<ChunkMeshDirty>(entity).is_some()
+        };
+
+        assert!(app.world().resource::<ChunkMap>().get(IVec3::ZERO).is_none());
+        assert!(is_dirty(&app, IVec3::X));
+        assert!(is_dirty(&app, IVec3::NEG_Z));
+        assert!(!is_dirty(&app, IVec3::new(2, 0, 0)), "not touching the despawned chunk");
+        assert!(!is_dirty(&app, IVec3::Y), "still generating");
     }
 }
@@ -1,19 +1,39 @@
 use block_mesh::{MergeVoxel, Voxel, VoxelVisibility};
 
-/// Basic voxel type with one byte of texture layers
-#[derive(Copy, Clone, Debug)]
+use crate::block_info::{BlockId, BlockInfoRegistry, AIR_BLOCK_ID};
+
+/// Basic voxel type, identifying its block by runtime block ID
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct BlockVoxel {
-    pub block_info_key_hash: u64,
-    pub is_air: bool,
+    pub block_id: BlockId,
     pub is_translucent: bool,
+    /// Blocks with any other model get meshed on their own, `greedy_quads` treats them as empty
+    pub is_cube: bool,
 }
 
 impl BlockVoxel {
     pub const AIR: Self = Self {
-        block_info_key_hash: 0,
-        is_air: true,
+        block_id: AIR_BLOCK_ID,
         is_translucent: true,
+        is_cube: false,
     };
+
+    /// Voxel of the block with the given runtime ID. IDs that don't resolve keep their ID, but get meshed
+    /// like the `MISSING_BLOCK_NAME` placeholder.
+    pub fn from_block_id(block_id: BlockId, block_info_registry: &BlockInfoRegistry) -> BlockVoxel {
+        match block_info_registry.resolve_block_id(block_id) {
+            Some(info) => BlockVoxel {
+                block_id,
+                is_translucent: info.is_translucent,
+                is_cube: info.model.is_cube(),
+            },
+            None => BlockVoxel::AIR,
+        }
+    }
+
+    pub fn is_air(&self) -> bool {
+        self.block_id == AIR_BLOCK_ID
+    }
 }
 
 impl Default for BlockVoxel {
@@ -22,28 +42,50 @@ impl Default for BlockVoxel {
     }
 }
 
-impl MergeVoxel for BlockVoxel {
-    type MergeValue = (bool, bool, u64);
-    type MergeValueFacingNeighbour = (bool, bool, u64);
+impl Voxel for BlockVoxel {
+    fn get_visibility(&self) -> VoxelVisibility {
+        match (self.is_air() || !self.is_cube, self.is_translucent) {
+            (true, _) => VoxelVisibility::Empty,
+            (false, true) => VoxelVisibility::Translucent,
+            _ => VoxelVisibility::Opaque,
+        }
+    }
+}
+
+/// Ambient occlusion & light of one side of a voxel, only filled in while meshing. Kept apart from the voxels,
+/// one buffer per side, see `occlusion::compute_face_occlusion` & `light::compute_face_light`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct VoxelFace {
+    /// Ambient occlusion of the side's corners, 2 bits per corner
+    pub occlusion: u8,
+    /// Light of the voxel the side faces, see `VoxelLight::to_bits`
+    pub light: u16,
+}
+
+/// Voxel as seen from a single side, which is what each `greedy_quads` pass meshes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FaceVoxel {
+    pub block: BlockVoxel,
+    pub face: VoxelFace,
+}
+
+impl MergeVoxel for FaceVoxel {
+    /// Faces only get merged when their corners are occluded the same way & they're lit alike, as the merged
+    /// quad can only carry the occlusion & light of its own corners
+    type MergeValue = (bool, BlockId, VoxelFace);
+    type MergeValueFacingNeighbour = (bool, BlockId);
 
     fn merge_value(&self) -> Self::MergeValue {
-        // (self.is_air as u16 + 1) * (self.block_atlas_id as u16 + 1)
-        (self.is_air, self.is_translucent, self.block_info_key_hash)
+        (self.block.is_translucent, self.block.block_id, self.face)
     }
 
     fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
-        // (self.is_air as u16 + 1) * (self.block_atlas_id as u16 + 1)
-        (self.is_air, self.is_translucent, self.block_info_key_hash)
+        (self.block.is_translucent, self.block.block_id)
     }
 }
 
-impl Voxel for BlockVoxel {
+impl Voxel for FaceVoxel {
     fn get_visibility(&self) -> VoxelVisibility {
-        match (self.is_air, self.is_translucent, self.block_info_key_hash) {
-            (true, _, _) => VoxelVisibility::Empty,
-            (_, _, 0) => VoxelVisibility::Empty,
-            (false, true, _) => VoxelVisibility::Translucent,
-            _ => VoxelVisibility::Opaque,
-        }
+        self.block.get_visibility()
     }
-}
\ No newline at end of file
+}